@@ -1,28 +1,68 @@
 //! This is where the core of the application is defined
 
-use std::env::current_dir;
-use std::fs::{read_dir, File};
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::env::{current_dir, var_os};
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
 
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{poll, Event, KeyCode, KeyEvent, KeyModifiers};
 use displaythis::Display;
 use ratatui::layout::{Constraint, Layout};
 use ratatui::style::{Color, Modifier, Style, Styled, Stylize};
-use ratatui::widgets::{Block, Borders, LineGauge};
+use ratatui::layout::Rect;
+use ratatui::widgets::block::Title;
+use ratatui::widgets::{Block, Borders, Clear, LineGauge, List, ListState, Paragraph, Wrap};
+use ratatui::buffer::Buffer;
 use ratatui::Frame;
 use regex::Regex;
 use tui_textarea::{Input, Key, TextArea};
 use tui_prompts::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
 use lazy_static::*;
-use clipboard::*;
 
+use crate::chapters;
+use crate::clip::Clipboard;
+use crate::config::{Config, Cue, CursorShape, CwdFallback, EditMode, EmptySave, EnterAction, Gauge, SplitConfig};
 use crate::errors::Result;
-use crate::term::{init_term, reset_term, Term};
+use crate::diff::{diff, Change};
+use crate::export::{concatenate, markdown};
+use crate::files;
+use crate::footnotes;
+use crate::highlight::Viewport;
+use crate::keys;
+use crate::lint;
+use crate::recent::Recent;
+use crate::replace;
+use crate::scan::Scan;
+use crate::session::{Session, ViewSettings};
+use crate::term::{init_term, reset_term, set_cursor, Term};
 
 lazy_static!{
-    static ref LONG_LINES : Regex = Regex::new(r"[^\n\S]{3,}").unwrap();
+    static ref SENTENCES  : Regex = Regex::new(r#"([.!?…]["'»)\]]*)(\s+["'«(\[]*)(\p{Ll})"#).unwrap();
+    static ref BLANK_RUNS : Regex = Regex::new(r"(?m)^[ \t]*\n(?:[ \t]*\n)+").unwrap();
+}
+
+/// The number of latency measures which are averaged
+const LATENCY_SAMPLES: usize = 20;
+
+/// How long the text stays in reverse video when it flashes
+const FLASH: Duration = Duration::from_millis(150);
+
+/// The runs of (at least `min_run`) whitespace characters separating the
+/// parts of a long line
+fn whitespace_runs(min_run: usize) -> Result<Regex> {
+    Ok(Regex::new(&format!(r"[^\n\S]{{{min_run},}}"))?)
+}
+
+/// The separators a long line gets split at: the matches of the configured
+/// pattern if there is one, the runs of whitespace otherwise
+fn split_runs(split: &SplitConfig, min_run: usize) -> Result<Regex> {
+    match split.pattern.as_deref() {
+        Some(pattern) => Ok(Regex::new(pattern)?),
+        None => whitespace_runs(min_run),
+    }
 }
 
 fn textarea<'a>(lines: Vec<String>, search: &str) -> TextArea<'a> {
@@ -37,11 +77,13 @@ fn textarea<'a>(lines: Vec<String>, search: &str) -> TextArea<'a> {
         .bg(ratatui::style::Color::DarkGray)
         .fg(ratatui::style::Color::White)
     );
+    text.set_cursor_style(Style::default());
     text.set_search_style(Style::default()
         .bg(Color::LightYellow)
         .fg(Color::Red)
     );
-    text.set_search_pattern(search).unwrap();
+    // an invalid pattern (e.g. from the configuration) highlights nothing
+    text.set_search_pattern(search).ok();
     // les trucs qu'on force a highlight
 
     text
@@ -50,7 +92,9 @@ fn textarea<'a>(lines: Vec<String>, search: &str) -> TextArea<'a> {
 
 pub struct App<'a> {
     term: Term,
-    state: AppState<'a>
+    state: AppState<'a>,
+    /// The shape last given to the terminal cursor
+    cursor: Option<CursorShape>,
 }
 
 pub struct AppState<'a> {
@@ -65,10 +109,204 @@ pub struct Data<'a> {
     tot : usize,
     curr: TextState<'a>,
     srch: TextState<'a>,
+    /// The pattern highlighted by default in the current directory
+    highlight: String,
+    /// Whether the search ignores the case
+    search_ci: bool,
+    /// Whether the search only matches whole words
+    search_word: bool,
     msg : String,
+    config: Config,
+    /// The buffer which is not currently displayed (the scratch buffer
+    /// unless `in_scratch` is set, in which case it holds the page)
+    scratch: TextArea<'a>,
+    in_scratch: bool,
+    confirm: Option<Confirm>,
+    /// The most recent status messages (newest last)
+    log : VecDeque<String>,
+    log_scroll: u16,
+    /// How far the list of the keys is scrolled
+    keys_scroll: u16,
+    /// The entry selected in the special character picker
+    chars: ListState,
+    /// The keys chosen by the user for the actions of the command mode
+    keymap: keys::KeyMap,
+    /// The filter of the command palette and the command selected in it
+    palette: TextState<'a>,
+    palette_state: ListState,
+    /// The rows matching the search pattern, shown by the focus filter
+    focus: Vec<usize>,
+    focus_state: ListState,
+    /// The directories opened recently, listed by the quick switcher
+    recent: Vec<String>,
+    recent_state: ListState,
+    /// When the pages of the directory must be counted again
+    next_reload: Option<Instant>,
+    /// When the buffer must be saved automatically
+    next_autosave: Option<Instant>,
+    /// The directory the pages come from (the prompt may hold another one
+    /// until it gets opened)
+    opened: String,
+    /// Whether the (huge) directory is spared the periodic counts and the
+    /// scans for the pages left to transcribe
+    lazy: bool,
+    /// The time until which the text is flashing (if it is)
+    flash: Option<Instant>,
+    /// The file (id or path) to open
+    file: TextState<'a>,
+    /// The id of the page to create
+    new_page: TextState<'a>,
+    /// The file open in the buffer when it is not a page
+    path: Option<PathBuf>,
+    /// The other file of the split view (if it is open)
+    pane: Option<Pane<'a>>,
+    /// The id of the file opened in the other pane
+    pane_id: TextState<'a>,
+    /// The number of words and characters of the buffer (None when it has
+    /// changed since they were counted)
+    counts: Option<(usize, usize)>,
+    /// The ids of the two files to compare
+    cmp : TextState<'a>,
+    compare: Option<Compare>,
+    /// The id of the file where the buffer gets saved as
+    save_as: TextState<'a>,
+    /// The line ending used by the current file on disk
+    eol : &'static str,
+    /// The message that was displayed before the last event
+    last_msg: String,
+    /// The number of times the next movement must be repeated
+    count: Option<usize>,
+    /// The files flagged for review along with their note
+    flags: BTreeMap<u32, String>,
+    note: TextState<'a>,
+    clipboard: Box<dyn Clipboard>,
+    /// The bytes of the current file as shown by the hex view
+    hex : Vec<u8>,
+    hex_scroll: u16,
+    /// The changes of the buffer since the file was saved, and how far their
+    /// list is scrolled
+    changes: Vec<Change>,
+    changes_scroll: u16,
+    /// The view settings of the current directory
+    view: ViewSettings,
+    /// The progress shown by the gauge
+    gauge: Gauge,
+    /// The line of the whole collection to go to
+    global: TextState<'a>,
+    /// The line of the file to go to
+    goto: TextState<'a>,
+    /// Whether the typed characters replace those under the cursor (in the
+    /// input mode) rather than being inserted
+    overwrite: bool,
+    /// The position where the selection started
+    anchor: (usize, usize),
+    /// The position where the (incremental) search started
+    search_origin: (usize, usize),
+    /// The search patterns used recently (the most recent last)
+    searches: Vec<String>,
+    /// The pattern recalled from the search history (if any) and the one
+    /// that was being typed before the history was browsed
+    recalled: Option<usize>,
+    draft: String,
+    /// The number of lines of the pages (along with the time they were
+    /// counted at)
+    line_counts: HashMap<u32, (SystemTime, usize)>,
+    /// The shortest run of whitespace the long lines are split at
+    min_run: usize,
+    /// The width the paragraphs get reflowed to
+    reflow_width: usize,
+    /// Whether the scanned images of the pages are shown next to their text,
+    /// and that of the current page (if it has one)
+    show_scan: bool,
+    scan: Option<Scan>,
+    /// The replacement of the search pattern (in the file or the directory)
+    replacement: TextState<'a>,
+    /// The pattern replaced in all the pages of the directory
+    pages_pattern: TextState<'a>,
+    /// The named registers (they only last for the session)
+    registers: BTreeMap<String, String>,
+    /// The name of the register being stored or recalled
+    register: TextState<'a>,
+    /// The text waiting for the name of its register
+    stash: String,
+    /// The number of digits of the page names
+    pad: usize,
+    /// The width chosen when the page names mix several ones
+    pad_width: TextState<'a>,
+    /// The pages which have a scanned image but no text yet
+    todo: Vec<u32>,
+    /// When the last event was received
+    received: Option<Instant>,
+    /// The page buffer has changes which are not saved yet
+    dirty: bool,
+    /// Hide the title bar and the status line
+    zen : bool,
+    /// Show the title bar and status line until the next event
+    peek: bool,
+    /// The last measures of the time between an event and the end of the
+    /// redraw (None unless they are measured)
+    latency: Option<VecDeque<Duration>>,
+}
+
+/// Two files shown side by side
+pub struct Compare {
+    left  : (u32, Vec<String>),
+    right : (u32, Vec<String>),
+    scroll: u16,
+}
+
+/// The file edited in the other half of the split view. The focused file
+/// is always the one in `Data::text`: switching the focus swaps the two.
+pub struct Pane<'a> {
+    /// The id of the file
+    x    : u32,
+    text : TextArea<'a>,
+    /// Whether the file has changes which are not saved yet
+    dirty: bool,
+    /// Whether this pane is drawn first (on the left half, or the top one)
+    first: bool,
+    /// Whether the two files are drawn one above the other
+    stacked: bool,
+}
+
+/// A question the user must answer with y/n before an action is performed
+pub struct Confirm {
+    question: String,
+    action  : Pending,
+    deadline: Option<Instant>,
+}
+
+/// The actions that require a confirmation
+#[derive(Debug, Clone)]
+pub enum Pending {
+    /// Overwrite the existing export document
+    Export,
+    /// Overwrite the existing Markdown export document
+    Markdown,
+    /// Overwrite an existing file with the content of the buffer
+    SaveAs(u32),
+    /// Split the buffer in several files at the headings
+    Split,
+    /// Quit the application
+    Quit,
+    /// Replace the given rows with their delimited version (the preview)
+    Columns(std::ops::Range<usize>, Vec<String>),
+    /// Replace the matches of the pattern in all the pages
+    Replace(Regex, String),
+    /// Move the pages into the directories of their chapters
+    Chapters(Vec<chapters::Move>),
+    /// Open the directory of the prompt, which has that many pages
+    Open(usize),
+    /// Load the page with the given id, discarding the changes of the buffer
+    Load(u32),
+    /// Load the file with the given path, discarding the changes of the
+    /// buffer
+    LoadPath(PathBuf),
 }
 
-pub struct View;
+pub struct View {
+    viewport: Viewport,
+}
 
 #[derive(Debug, Clone, Copy, Display)]
 pub enum Mode {
@@ -86,15 +324,69 @@ pub enum Mode {
     History,
     #[display("COMMAND")]
     Command,
+    #[display("CONFIRM")]
+    Confirm,
+    #[display("MESSAGES")]
+    Log,
+    #[display("CHARS")]
+    Chars,
+    #[display("PALETTE")]
+    Palette,
+    #[display("KEYS")]
+    Keys,
+    #[display("RECENT")]
+    Recent,
+    #[display("FOCUS")]
+    Focus,
+    #[display("COMPARE")]
+    Compare,
+    #[display("VIEW")]
+    SideBySide,
+    #[display("SAVE-AS")]
+    SaveAs,
+    #[display("NEW")]
+    NewPage,
+    #[display("PANE")]
+    Pane,
+    #[display("MESSAGE")]
+    Message,
+    #[display("FLAG")]
+    Flag,
+    #[display("HEX")]
+    Hex,
+    #[display("GLOBAL")]
+    Global,
+    #[display("GOTO")]
+    Goto,
+    #[display("REPLACE")]
+    Replace,
+    #[display("REPLACE-ALL")]
+    ReplacePattern,
+    #[display("REPLACE-ALL")]
+    ReplacePages,
+    #[display("CUTS")]
+    Cuts,
+    #[display("REFLOW")]
+    Reflow,
+    #[display("DIFF")]
+    Diff,
+    #[display("PAD")]
+    Pad,
+    #[display("STORE")]
+    Store,
+    #[display("RECALL")]
+    Recall,
     #[display("QUIT")]
     Quit
 }
 
 impl<'a> App<'a> {
-    pub fn new() -> Result<Self> {
+    /// Creates the application, working in the given directory (if any)
+    pub fn new(dir: Option<PathBuf>) -> Result<Self> {
         Ok(Self { 
             term: init_term()?, 
-            state: AppState::new()
+            state: AppState::new(dir),
+            cursor: None,
         })
     }
 
@@ -104,12 +396,23 @@ impl<'a> App<'a> {
             let state= &mut self.state;
 
             if let Err(e) = term.draw(|f| state.ui(f)) {
-                state.data.msg = format!("{e}");
+                state.data.notify(format!("{e}"));
+            }
+            if let (Some(samples), Some(received)) = (state.data.latency.as_mut(), state.data.received.take()) {
+                if samples.len() >= LATENCY_SAMPLES {
+                    samples.pop_front();
+                }
+                samples.push_back(received.elapsed());
+            }
+            let shape = state.data.config.cursor.mode(&state.data.mode_name());
+            if self.cursor != Some(shape) {
+                match set_cursor(term, shape) {
+                    Ok(()) => self.cursor = Some(shape),
+                    Err(e) => state.data.notify(format!("{e}")),
+                }
             }
             if let Err(e) = state.input() {
-                state.data.msg = format!("{e}");
-            } else {
-                state.data.msg = String::new();
+                state.data.notify(format!("{e}"));
             }
 
             if matches!(state.mode(), Mode::Quit) {
@@ -125,11 +428,42 @@ impl Drop for App<'_> {
 }
 
 impl AppState<'_> {
-    fn new() -> Self {
-        let data = Data::new();
+    fn new(dir: Option<PathBuf>) -> Self {
+        let (config, msg) = match Config::load() {
+            Ok(config) => (config, String::new()),
+            Err(e)     => (Config::default(), format!("{e}")),
+        };
+        let autoload = config.autoload.unwrap_or(dir.is_some());
+        let (cwd, lost) = match dir.map(Ok).unwrap_or_else(current_dir) {
+            Ok(cwd) => (cwd, None),
+            Err(e)  => (var_os("HOME").map_or_else(|| PathBuf::from("/"), PathBuf::from), Some(e)),
+        };
+        let mut data = Data::new(config, &cwd);
         let view = View::new();
+        if !msg.is_empty() {
+            data.notify(msg);
+        }
+        if let Some(e) = lost {
+            match data.config.cwd_fallback {
+                CwdFallback::Home => {
+                    data.notify(format!("The current directory cannot be accessed ({e}), using {} instead", cwd.display()));
+                },
+                CwdFallback::Prompt => {
+                    data.notify(format!("The current directory cannot be accessed ({e}), please choose one"));
+                    data.mode = Mode::OpenDir;
+                },
+            }
+        }
 
-        Self { data, view }
+        let mut state = Self { data, view };
+        state.load_keymap();
+        state.report_conflicts(false);
+        if autoload && matches!(state.mode(), Mode::Command) {
+            if let Err(e) = state.open() {
+                state.data.notify(format!("{e}"));
+            }
+        }
+        state
     }
     fn mode(&self) -> Mode {
         self.data.mode
@@ -144,7 +478,36 @@ impl AppState<'_> {
         view.ui(data, frame)
     }
     fn input(&mut self) -> Result<()> {
+        if let Some(timeout) = self.timeout() {
+            if !poll(timeout)? {
+                return self.tick();
+            }
+        }
         let input = crossterm::event::read()?;
+        // the terminals reporting the repeats also report the releases,
+        // which none of the modes handles
+        if let Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Release, ..}) = input {
+            return Ok(());
+        }
+        self.data.received = Some(Instant::now());
+        self.data.peek = false;
+        self.data.last_msg = std::mem::take(&mut self.data.msg);
+
+        if let Some(mode) = self.cycle(&input) {
+            self.enter(mode);
+            return Ok(());
+        }
+        if let Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('q'), modifiers: KeyModifiers::CONTROL, ..}) = input {
+            if !matches!(self.mode(), Mode::Command | Mode::Confirm) {
+                let mode = self.mode().to_string().to_lowercase();
+                if self.data.config.quit_from.contains(&mode) {
+                    return self.quit();
+                }
+                let question = if self.data.unsaved() { "Quit (unsaved changes are lost) ?" } else { "Quit ?" };
+                self.confirm(question.to_string(), Pending::Quit);
+                return Ok(());
+            }
+        }
 
         match self.mode() {
             Mode::OpenDir   => self.open_input(input),
@@ -153,82 +516,310 @@ impl AppState<'_> {
             Mode::Selection => self.select_input(input),
             Mode::Search    => self.search_input(input),
             Mode::History   => self.history_input(input),
-            Mode::Command   => self.command_input(input),
+            Mode::Command   => match self.data.keymap.translate(input) {
+                Some(input) => self.command_input(input),
+                None        => Ok(()),
+            },
+            Mode::Confirm   => self.confirm_input(input),
+            Mode::Log       => self.log_input(input),
+            Mode::Chars     => self.chars_input(input),
+            Mode::Palette   => self.palette_input(input),
+            Mode::Keys      => self.keys_input(input),
+            Mode::Recent    => self.recent_input(input),
+            Mode::Focus     => self.focus_input(input),
+            Mode::Compare   => self.compare_input(input),
+            Mode::SideBySide=> self.side_by_side_input(input),
+            Mode::SaveAs    => self.save_as_input(input),
+            Mode::NewPage   => self.new_page_input(input),
+            Mode::Pane      => self.pane_input(input),
+            Mode::Message   => self.message_input(input),
+            Mode::Flag      => self.flag_input(input),
+            Mode::Global    => self.global_input(input),
+            Mode::Goto      => self.goto_input(input),
+            Mode::Replace   => self.replace_input(input),
+            Mode::ReplacePattern => self.replace_pattern_input(input),
+            Mode::ReplacePages => self.replace_pages_input(input),
+            Mode::Cuts      => self.cuts_input(input),
+            Mode::Reflow    => self.reflow_input(input),
+            Mode::Diff      => self.diff_input(input),
+            Mode::Pad       => self.pad_input(input),
+            Mode::Store     => self.store_input(input),
+            Mode::Recall    => self.recall_input(input),
+            Mode::Hex       => self.hex_input(input),
             Mode::Quit      => self.quit_input(input),
         }
     }
+    /// The editable mode to switch to when the input is a cycle key
+    fn cycle(&self, input: &Event) -> Option<Mode> {
+        let cycle = &self.data.config.cycle;
+        let current = match self.mode() {
+            Mode::Command   => None,
+            Mode::Input     => Some(EditMode::Input),
+            Mode::Search    => Some(EditMode::Search),
+            Mode::Selection => Some(EditMode::Selection),
+            _ => return None,
+        };
+        let len = cycle.modes.len();
+        if len == 0 {
+            return None;
+        }
+        let pos = current.and_then(|m| cycle.modes.iter().position(|x| *x == m));
+        let next = if cycle.forward.matches(input) {
+            pos.map_or(0, |i| (i + 1) % len)
+        } else if cycle.backward.matches(input) {
+            pos.map_or(len, |i| i + len) - 1
+        } else {
+            return None;
+        };
+        cycle.modes.get(next).map(|m| match m {
+            EditMode::Input     => Mode::Input,
+            EditMode::Search    => Mode::Search,
+            EditMode::Selection => Mode::Selection,
+        })
+    }
+    /// Switches to an editable mode
+    fn enter(&mut self, mode: Mode) {
+        if matches!(self.mode(), Mode::Selection) {
+            self.data.text.cancel_selection();
+        }
+        self.set_mode(mode);
+        match mode {
+            Mode::Search    => {
+                self.data.search_origin = self.data.text.cursor();
+                self.data.recalled = None;
+                self.data.srch.move_end();
+            },
+            Mode::Selection => {
+                self.data.anchor = self.data.text.cursor();
+                self.data.text.start_selection();
+            },
+            _ => { /* nothing to prepare */ }
+        }
+    }
     fn open_input(&mut self, input: Event) -> Result<()> {
         match input {
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
                 self.set_mode(Mode::Command); 
             },
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
-                let cwd = self.data.cwd.value();
-                self.data.tot = read_dir(cwd)?
-                    .filter_map(|e| e.ok())
-                    .filter(|e| e.path().is_file() )
-                    .count();
-
-                self.load(0)?;
-                self.set_mode(Mode::Command); 
+                self.open()?;
             },
             Event::Key(event) => { self.data.cwd.handle_key_event(event); },
             _ => { /* ignore */}
         }
         Ok(())
     }
+    /// Counts the pages of the directory of the prompt and opens it (after
+    /// a confirmation when it is huge)
+    fn open(&mut self) -> Result<()> {
+        let tot = self.count()?;
+        if self.data.config.huge_directory.is_some_and(|max| tot > max) {
+            self.confirm(
+                format!("{tot} pages found, open the directory (l: in lazy mode) ?"),
+                Pending::Open(tot));
+            Ok(())
+        } else {
+            self.open_dir(tot, false)
+        }
+    }
+    /// Lists the directories opened recently so that one can be picked
+    fn show_recent(&mut self) {
+        match Recent::load() {
+            Ok(recent) if recent.dirs.is_empty() => {
+                self.data.notify("No directory was opened recently");
+            },
+            Ok(recent) => {
+                self.data.recent = recent.dirs;
+                self.data.recent_state.select(Some(0));
+                self.set_mode(Mode::Recent);
+            },
+            Err(e) => {
+                self.data.notify(format!("cannot read the recent directories: {e}"));
+            },
+        }
+    }
+    /// Adds the directory which has just been opened to the recent ones
+    fn remember_dir(&mut self) -> Result<()> {
+        let mut recent = Recent::load().unwrap_or_default();
+        recent.push(Path::new(&self.data.opened));
+        recent.save()
+    }
+    /// Opens the directory of the prompt, which has the given number of
+    /// pages. In lazy mode, they are neither counted again periodically nor
+    /// scanned for those left to transcribe.
+    fn open_dir(&mut self, tot: usize, lazy: bool) -> Result<()> {
+        self.data.tot = tot;
+        self.data.lazy = lazy;
+        self.data.opened = self.data.cwd.value().to_string();
+        self.restore_view();
+        if lazy {
+            // the position in a huge collection hardly means anything
+            self.data.gauge = Gauge::File;
+        }
+        self.data.todo = self.untranscribed()?;
+        if self.data.config.reset_search {
+            *self.data.srch.value_mut() = self.data.highlight.clone();
+            self.data.srch.move_end();
+        }
+        self.set_mode(Mode::Command); 
+        if !self.detect_pad()? && !self.resume()? {
+            if let Some(x) = self.first_page()? {
+                self.load(x)?;
+                self.store_position()?;
+            }
+        }
+        self.remember_dir()
+    }
     fn curr_input(&mut self, input: Event) -> Result<()> {
         match input {
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
                 self.set_mode(Mode::Command); 
             },
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
-                let curr = self.data.curr.value().parse()?;
-                self.load(curr)?;
-                self.set_mode(Mode::Command); 
+                let file = self.data.file.value().trim().to_string();
+                match file.parse() {
+                    Ok(x) => self.load(x)?,
+                    // anything but an id is the path of a file (relative to
+                    // the directory of the pages)
+                    Err(_) => self.load_path(Path::new(self.data.cwd.value()).join(file))?,
+                }
+                // unless the unsaved changes must be confirmed first
+                if matches!(self.mode(), Mode::OpenFile) {
+                    self.set_mode(Mode::Command);
+                }
             },
-            Event::Key(event) => { self.data.curr.handle_key_event(event); },
+            Event::Key(event) => { self.data.file.handle_key_event(event); },
             _ => { /* ignore */}
         }
         Ok(())
     }
     fn input_input(&mut self, input: Event) -> Result<()> {
+        // tui-textarea knows nothing about the insert key
+        if let Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Insert, ..}) = input {
+            self.data.overwrite ^= true;
+            return Ok(());
+        }
         let input = input.into();
         match input {
             Input { key: Key::Esc, .. } => { self.set_mode(Mode::Command); },
-            _ =>  { self.data.text.input(input); }
+            Input { key: Key::Char(c), ctrl: false, alt: false, .. } if self.data.overwrite => {
+                // the character under the cursor (if any) gets replaced
+                let (row, col) = self.data.text.cursor();
+                if col < self.data.text.lines()[row].chars().count() {
+                    self.data.text.delete_next_char();
+                }
+                self.data.text.insert_char(c);
+                self.modified();
+            },
+            _ =>  {
+                if self.data.text.input(input) {
+                    self.modified();
+                }
+            }
         }
         Ok(())
     }
     fn select_input(&mut self, input: Event) -> Result<()> {
-        if let Some(input) = self.movement(input) {
+        if let Some(input) = self.counted_movement(input) {
             match input {
                 Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { self.set_mode(Mode::Command);  },
-                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('x'), ..}) => { self.data.text.cut(); self.set_mode(Mode::Command); },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('x'), ..}) => {
+                    if self.data.text.cut() {
+                        self.modified();
+                    }
+                    self.set_mode(Mode::Command);
+                },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('>'), ..}) => { self.set_mode(Mode::Command); self.copy_quoted()?; },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('*'), ..}) => {
+                    self.set_mode(Mode::Command);
+                    self.data.text.copy();
+                    let text = self.data.text.yank_text();
+                    let count = text.chars().count();
+                    self.data.clipboard.set(text)?;
+                    self.data.notify(format!("Selection ({count} characters) copied to the clipboard"));
+                },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('J'), ..}) => {
+                    let (row, _) = self.data.text.cursor();
+                    let (anchor, _) = self.data.anchor;
+                    self.data.text.cancel_selection();
+                    self.set_mode(Mode::Command);
+                    // a selection within a single line joins it with the next one
+                    let start = row.min(anchor);
+                    self.join_lines(start..(row.max(anchor) + 1).max(start + 2));
+                },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('U'), ..}) => { self.change_case(Case::Upper); },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('u'), ..}) => { self.change_case(Case::Lower); },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('T'), ..}) => { self.change_case(Case::Title); },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('t'), ..}) => {
+                    let (row, _) = self.data.text.cursor();
+                    let (anchor, _) = self.data.anchor;
+                    self.data.text.cancel_selection();
+                    self.set_mode(Mode::Command);
+                    self.trim_trailing(row.min(anchor)..row.max(anchor) + 1);
+                },
+                Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('"'), ..}) => {
+                    self.data.text.copy();
+                    self.data.stash = self.data.text.yank_text();
+                    self.set_mode(Mode::Store);
+                },
                 _ => { /* ignore */}
             }
         }
         Ok(())
     }
+    /// Accumulates the digits of a count prefix, then repeats the movement
+    /// that follows as many times. Returns the input if it is neither.
+    fn counted_movement(&mut self, input: Event) -> Option<Event> {
+        if let Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char(c @ '0'..='9'), modifiers: KeyModifiers::NONE, ..}) = input {
+            if c != '0' || self.data.count.is_some() {
+                let digit = c as usize - '0' as usize;
+                self.data.count = Some(self.data.count.unwrap_or_default().saturating_mul(10).saturating_add(digit));
+                return None;
+            }
+        }
+        let count = self.data.count.take().unwrap_or(1);
+        match self.movement(input.clone()) {
+            None => {
+                for _ in 1..count {
+                    self.movement(input.clone());
+                }
+                None
+            },
+            other => other,
+        }
+    }
     fn movement(&mut self, input: Event) -> Option<Event> {
+        // a held key only repeats the movements (unless the terminal already
+        // sends a press along with each repeat)
+        let (input, repeat) = match input {
+            Event::Key(key @ KeyEvent{kind: crossterm::event::KeyEventKind::Repeat, ..}) => {
+                if !self.data.config.key_repeat {
+                    return None;
+                }
+                (Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, ..key}), true)
+            },
+            input => (input, false),
+        };
         match input {
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Right, modifiers: KeyModifiers::CONTROL, ..}) |
-            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('w'), ..}) => { 
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('w'), modifiers: KeyModifiers::NONE, ..}) => { 
                 self.data.text.move_cursor(tui_textarea::CursorMove::WordForward); 
                 None
             },
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Left, modifiers: KeyModifiers::CONTROL, ..}) |
-            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('b'), ..}) => { 
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('b'), modifiers: KeyModifiers::NONE, ..}) => { 
                 self.data.text.move_cursor(tui_textarea::CursorMove::WordBack); 
                 None
             },
             //
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('u'), modifiers: KeyModifiers::CONTROL, ..}) |
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('{'), ..}) |
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::PageUp, ..}) => { 
                 self.data.text.move_cursor(tui_textarea::CursorMove::ParagraphBack); 
                 None
             },
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('d'), modifiers: KeyModifiers::CONTROL, ..}) |
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('}'), ..}) |
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::PageDown, ..}) => { 
                 self.data.text.move_cursor(tui_textarea::CursorMove::ParagraphForward); 
                 None
@@ -260,6 +851,7 @@ impl AppState<'_> {
                 self.data.text.move_cursor(tui_textarea::CursorMove::Down); 
                 None
             },
+            _ if repeat => None,
             _ => Some(input)
         }
     }
@@ -267,188 +859,3228 @@ impl AppState<'_> {
     fn search_input(&mut self, input: Event) -> Result<()> {
         match input {
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.remember_search();
                 self.set_mode(Mode::Command); 
             },
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, modifiers: KeyModifiers::SHIFT, ..}) => {
-                self.data.text.set_search_pattern(self.data.srch.value())?;
+                self.data.text.set_search_pattern(self.data.search())?;
                 self.data.text.search_back(false);
+                self.remember_search();
             },
             Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => {
-                self.data.text.set_search_pattern(self.data.srch.value())?;
+                self.data.text.set_search_pattern(self.data.search())?;
                 self.data.text.search_forward(false);
+                self.remember_search();
             }, 
-            Event::Key(event) => { self.data.srch.handle_key_event(event); },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Up, ..}) => {
+                let older = match self.data.recalled {
+                    None    => self.data.searches.len().checked_sub(1),
+                    Some(x) => Some(x.saturating_sub(1)),
+                };
+                if let Some(x) = older {
+                    if self.data.recalled.is_none() {
+                        self.data.draft = self.data.srch.value().to_string();
+                    }
+                    self.recall_search(Some(x));
+                }
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Down, ..}) => {
+                if let Some(x) = self.data.recalled {
+                    self.recall_search(Some(x + 1).filter(|x| *x < self.data.searches.len()));
+                }
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('c'), modifiers: KeyModifiers::ALT, ..}) => {
+                self.data.search_ci = !self.data.search_ci;
+                self.search_incrementally();
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('w'), modifiers: KeyModifiers::ALT, ..}) => {
+                self.data.search_word = !self.data.search_word;
+                self.search_incrementally();
+            },
+            Event::Key(event) => {
+                let before = self.data.srch.value().to_string();
+                self.data.srch.handle_key_event(event);
+                if self.data.srch.value() != before {
+                    self.search_incrementally();
+                }
+            },
             _ => { /* ignore */}
         }
         Ok(())
     }
+    /// Puts the given pattern of the search history in the prompt (or the
+    /// one that was being typed when there is none)
+    fn recall_search(&mut self, x: Option<usize>) {
+        self.data.recalled = x;
+        *self.data.srch.value_mut() = match x {
+            Some(x) => self.data.searches[x].clone(),
+            None    => self.data.draft.clone(),
+        };
+        self.data.srch.move_end();
+        self.search_incrementally();
+    }
+    /// Adds the pattern of the prompt to the search history, unless it is
+    /// empty or it is the last one already
+    fn remember_search(&mut self) {
+        let pattern = self.data.srch.value().to_string();
+        self.data.recalled = None;
+        if pattern.is_empty() || self.data.searches.last() == Some(&pattern) {
+            return;
+        }
+        self.data.searches.push(pattern);
+        let excess = self.data.searches.len().saturating_sub(self.data.config.search_history);
+        self.data.searches.drain(..excess);
+        if self.data.config.persist_search_history {
+            let mut recent = Recent::load().unwrap_or_default();
+            recent.searches = self.data.searches.clone();
+            if let Err(e) = recent.save() {
+                self.data.notify(format!("cannot store the search history: {e}"));
+            }
+        }
+    }
+    /// Highlights the pattern as it is typed and moves to its first match
+    /// from where the search started. The pattern is often invalid while it
+    /// is being typed (e.g. after an opening parenthesis): nothing is
+    /// highlighted then, until it becomes valid again.
+    fn search_incrementally(&mut self) {
+        let (row, col) = self.data.search_origin;
+        self.data.text.move_cursor(tui_textarea::CursorMove::Jump(row.min(u16::MAX as usize) as u16, col.min(u16::MAX as usize) as u16));
+        match self.data.text.set_search_pattern(self.data.search()) {
+            Ok(())  => { self.data.text.search_forward(true); },
+            Err(_)  => { self.data.text.set_search_pattern("").ok(); },
+        }
+    }
     fn history_input(&mut self, input: Event) -> Result<()> {
         let input = input.into();
         match input {
             Input { key: Key::Esc, .. }       => { self.set_mode(Mode::Command); },
-            Input { key: Key::Char('u'), .. } => { self.data.text.undo(); },
-            Input { key: Key::Char('r'), .. } => { self.data.text.redo(); },
-            _ => { /* ignore */}
+            Input { key: Key::Char('u'), .. } => { self.undo(); },
+            Input { key: Key::Char('r'), .. } => { self.redo(); },
+            _ => { /* ignore */ }
         }
         Ok(())
     }
+    /// Undoes the last change of the buffer (if any)
+    fn undo(&mut self) {
+        if self.data.text.undo() {
+            self.modified();
+        } else {
+            self.data.notify("Nothing to undo");
+        }
+    }
+    /// Redoes the last undone change of the buffer (if any)
+    fn redo(&mut self) {
+        if self.data.text.redo() {
+            self.modified();
+        } else {
+            self.data.notify("Nothing to redo");
+        }
+    }
     fn command_input(&mut self, input: Event) -> Result<()> {
-        if let Some(input) = self.movement(input) {
+        if let Some(input) = self.counted_movement(input) {
             let input = input.into();
             match input {
-                Input { key: Key::Char('q'), .. } => { self.set_mode(Mode::Quit); },
+                Input { key: Key::Char('q'), .. } => {
+                    if self.data.unsaved() {
+                        self.data.notify("Unsaved changes, press Q to discard them and quit");
+                    } else {
+                        self.quit()?;
+                    }
+                },
+                Input { key: Key::Char('Q'), .. } => { self.quit()?; },
+                Input { key: Key::Char(':'), .. } => {
+                    *self.data.palette.value_mut() = String::new();
+                    self.data.palette_state.select(Some(0));
+                    self.set_mode(Mode::Palette);
+                },
+                Input { key: Key::Char('o'), ctrl: true, .. } => { self.show_recent(); },
                 Input { key: Key::Char('o'), .. } => { self.set_mode(Mode::OpenDir); self.data.cwd.move_end(); },
-                Input { key: Key::Char('f'), .. } => { self.set_mode(Mode::OpenFile); self.data.curr.move_end(); },
+                Input { key: Key::Char('+'), .. } => {
+                    let next = self.page_names()?.iter()
+                        .filter_map(|name| name.parse::<u32>().ok())
+                        .max()
+                        .map_or(0, |x| x + 1);
+                    *self.data.new_page.value_mut() = self.id(next);
+                    self.data.new_page.move_end();
+                    self.set_mode(Mode::NewPage);
+                },
+                Input { key: Key::Char('f'), .. } => {
+                    self.set_mode(Mode::OpenFile);
+                    *self.data.file.value_mut() = self.data.curr.value().to_string();
+                    self.data.file.move_end();
+                },
+                Input { key: Key::Char('r'), ctrl: true, .. } => { self.redo(); },
+                Input { key: Key::Char('r'), .. } => {
+                    if self.data.text.search_pattern().is_none() {
+                        self.data.notify("No search pattern");
+                    } else {
+                        self.set_mode(Mode::Replace);
+                        self.data.replacement.move_end();
+                    }
+                },
+                Input { key: Key::Char('R'), .. } => {
+                    if self.data.pages_pattern.value().is_empty() {
+                        if let Some(pattern) = self.data.text.search_pattern() {
+                            *self.data.pages_pattern.value_mut() = pattern.as_str().to_string();
+                        }
+                    }
+                    self.set_mode(Mode::ReplacePattern);
+                    self.data.pages_pattern.move_end();
+                },
+                Input { key: Key::Char('g'), ctrl: true, .. } => { self.set_mode(Mode::Global); self.data.global.move_end(); },
+                Input { key: Key::Char('g'), .. } => { self.set_mode(Mode::Goto); self.data.goto = TextState::new(); },
+                Input { key: Key::Char('G'), .. } => { self.data.text.move_cursor(tui_textarea::CursorMove::Bottom); },
                 Input { key: Key::Char('i'), .. } => { self.set_mode(Mode::Input); },
+                Input { key: Key::Char('A'), .. } => {
+                    self.data.text.move_cursor(tui_textarea::CursorMove::Bottom);
+                    self.data.text.move_cursor(tui_textarea::CursorMove::End);
+                    self.set_mode(Mode::Input);
+                },
+                Input { key: Key::Char('a'), .. } => { self.goto_empty_line(); },
+                Input { key: Key::Char('J'), .. } => {
+                    let (row, _) = self.data.text.cursor();
+                    self.join_lines(row..row + 2);
+                },
+                Input { key: Key::Char('t'), .. } => { self.trim_trailing(0..usize::MAX); },
                 Input { key: Key::Char('h'), .. } => { self.set_mode(Mode::History); },
-                Input { key: Key::Char('/'), .. } => { self.set_mode(Mode::Search); self.data.srch.move_end(); },
+                Input { key: Key::Char('/'), .. } => { self.enter(Mode::Search); },
+                Input { key: Key::Char('v'), ctrl: true, .. } => { self.paste(); },
                 Input { key: Key::Char('*'), .. } => {
                     let text = self.data.text.lines().join("\n");
-                    let mut clipboard: ClipboardContext = ClipboardProvider::new().unwrap();
-                    clipboard.set_contents(text).unwrap();
-                    self.data.msg = "Filed Copied to Clipboard".to_string();
+                    self.data.clipboard.set(text)?;
+                    self.data.notify("Whole file copied to the clipboard");
                 },
                 //
                 Input { key: Key::Char('n'), .. } => { self.next()?; },
                 Input { key: Key::Char('p'), .. } => { self.prev()?; },
                 Input { key: Key::Char('s'), ctrl: true, .. } => { self.save()?; },
+                Input { key: Key::Char('s'), .. } => { self.squeeze_blank_lines(); },
+                Input { key: Key::Char('u'), .. } => { self.undo(); },
+                Input { key: Key::Enter, .. } => {
+                    match self.data.config.enter {
+                        EnterAction::None     => { /* do nothing */ },
+                        EnterAction::Next     => { self.next()?; },
+                        EnterAction::Save     => { self.save()?; },
+                        EnterAction::SaveNext => { self.save()?; self.next()?; },
+                    }
+                },
+                Input { key: Key::Char('e'), .. } => { self.export()?; },
+                Input { key: Key::Char('E'), .. } => { self.export_markdown()?; },
+                Input { key: Key::Char('S'), .. } => { self.toggle_scratch()?; },
+                Input { key: Key::Char('M'), .. } => { self.set_mode(Mode::Log); self.data.log_scroll = 0; },
+                Input { key: Key::Char('?'), .. } => { self.set_mode(Mode::Keys); self.data.keys_scroll = 0; },
+                Input { key: Key::Char('"'), .. } => {
+                    self.data.stash = self.data.text.lines().join("\n");
+                    self.set_mode(Mode::Store);
+                },
+                Input { key: Key::Char('\''), .. } => {
+                    if self.data.registers.is_empty() {
+                        self.data.notify("No register");
+                    } else {
+                        self.set_mode(Mode::Recall);
+                    }
+                },
+                Input { key: Key::Char('@'), .. } => { self.list_registers(); },
+                Input { key: Key::Char('!'), .. } => {
+                    if self.data.last_msg.is_empty() {
+                        self.data.notify("No message to expand");
+                    } else {
+                        self.set_mode(Mode::Message);
+                    }
+                },
+                Input { key: Key::Char('c'), .. } => { self.set_mode(Mode::Chars); self.data.chars.select(Some(0)); },
+                Input { key: Key::Char('F'), .. } => { self.focus(); },
+                Input { key: Key::Char('&'), .. } => { self.search_word()?; },
+                Input { key: Key::Char('C'), .. } => { self.set_mode(Mode::Compare); self.data.cmp.move_end(); },
+                Input { key: Key::Char('Y'), .. } => { self.toggle_pane(); },
+                Input { key: Key::Char('y'), .. } => { self.split_adjacent()?; },
+                Input { key: Key::Char('w'), ctrl: true, .. } => { self.switch_pane(); },
+                Input { key: Key::Char('W'), .. } => { self.set_mode(Mode::SaveAs); self.data.save_as.move_end(); },
+                Input { key: Key::Char('B'), .. } => { self.ask_split_at_headings()?; },
+                Input { key: Key::Char('m'), .. } => { self.toggle_flag()?; },
+                Input { key: Key::Char('X'), .. } => { self.export_flags()?; },
+                Input { key: Key::Char('T'), .. } => { self.apply_markup(); },
+                Input { key: Key::Char('x'), .. } => {
+                    self.data.hex = std::fs::read(self.buffer_fname()?)?;
+                    self.data.hex_scroll = 0;
+                    self.set_mode(Mode::Hex);
+                },
                 //
-                Input { key: Key::Char('#'), .. } => { self.data.text.insert_str("###\n"); },
-                Input { key: Key::Char('l'), .. } => { self.split_long_lines(); },
+                Input { key: Key::Char('#'), .. } => { self.data.text.insert_str("###\n"); self.modified(); },
+                Input { key: Key::Char('='), .. } => { self.insert_page_break(); },
+                Input { key: Key::Char('['), .. } => { self.insert_footnote(); },
+                Input { key: Key::Char(']'), .. } => { self.jump_footnote(); },
+                Input { key: Key::Char('l'), .. } => { self.split_long_lines()?; },
+                Input { key: Key::Char('L'), .. } => { self.split_paragraph()?; },
+                Input { key: Key::Char('j'), .. } => { self.set_mode(Mode::Reflow); },
+                Input { key: Key::Char('d'), .. } => { self.show_changes()?; },
+                Input { key: Key::Char('I'), .. } => {
+                    self.data.show_scan = !self.data.show_scan;
+                    self.load_scan();
+                },
+                Input { key: Key::Char('P'), .. } => {
+                    // an invalid pattern is reported rather than previewing nothing
+                    split_runs(&self.data.config.split, self.data.min_run)?;
+                    self.set_mode(Mode::Cuts);
+                },
+                Input { key: Key::Char('K'), .. } => { self.check_complete()?; },
+                Input { key: Key::Char('k'), .. } => { self.report_conflicts(true); },
+                Input { key: Key::Char('H'), .. } => { self.ask_chapters()?; },
+                Input { key: Key::Char('V'), .. } => { self.lint()?; },
+                Input { key: Key::Char('z'), .. } => { self.data.zen ^= true; },
+                Input { key: Key::Tab, .. } if self.data.pane.is_some() => { self.switch_pane(); },
+                Input { key: Key::Tab, .. }       => { self.data.peek = self.data.zen; },
+                Input { key: Key::Char('D'), .. } => { self.toggle_latency(); },
+                Input { key: Key::Char('O'), .. } => { self.reload_config(); },
+                Input { key: Key::Char('Z'), .. } => { self.reset_settings()?; },
+                Input { key: Key::Char('%'), .. } => { self.toggle_gauge(); },
+                Input { key: Key::Char('U'), .. } => { self.capitalize(); },
+                Input { key: Key::Char('|'), .. } => { self.ask_columns()?; },
+                Input { key: Key::Char('N'), .. } => { self.data.view.line_numbers ^= true; self.store_view()?; },
+                Input { key: Key::Char('.'), .. } => { self.data.view.whitespace ^= true; self.store_view()?; },
+                Input { key: Key::Char('_'), .. } => { self.data.view.trailing ^= true; self.store_view()?; },
                 //
                 Input { key: Key::Char(' '), .. } |
-                Input { key: Key::Char('v'), .. } => { self.set_mode(Mode::Selection); self.data.text.start_selection(); } 
+                Input { key: Key::Char('v'), .. } => { 
+                    self.set_mode(Mode::Selection); 
+                    self.data.anchor = self.data.text.cursor();
+                    self.data.text.start_selection(); 
+                } 
                 _ =>  { /* do nothing */ }
             }
         }
         Ok(())
     }
-    fn quit_input(&mut self, _input: Event) -> Result<()> {
+    fn confirm_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        match input {
+            Input { key: Key::Char('y'), .. } |
+            Input { key: Key::Char('Y'), .. } => {
+                self.set_mode(Mode::Command);
+                if let Some(confirm) = self.data.confirm.take() {
+                    self.perform(confirm.action)?;
+                }
+            },
+            Input { key: Key::Char('l'), .. } |
+            Input { key: Key::Char('L'), .. } if matches!(self.data.confirm, Some(Confirm { action: Pending::Open(_), .. })) => {
+                self.set_mode(Mode::Command);
+                if let Some(Confirm { action: Pending::Open(tot), .. }) = self.data.confirm.take() {
+                    self.open_dir(tot, true)?;
+                }
+            },
+            Input { key: Key::Char('n'), .. } |
+            Input { key: Key::Char('N'), .. } |
+            Input { key: Key::Esc, .. } => {
+                self.set_mode(Mode::Command);
+                if let Some(confirm) = self.data.confirm.take() {
+                    self.abandon(confirm.action);
+                }
+                self.data.notify("Cancelled");
+            },
+            _ => { /* ignore */ }
+        }
         Ok(())
     }
-
-    fn save(&self) -> Result<()> {
-        let cwd = PathBuf::from_str(self.data.cwd.value()).unwrap();
-        let x: u32 = self.data.curr.value().parse()?;
-        let fname = cwd.join(format!("{x:03}.txt"));
-        std::fs::remove_file(&fname)?;
-
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(false)
-            .open(fname)?;
-
-        let mut wrt = BufWriter::new(file);
-        let text = self.data.text.lines().join("\n");
-        wrt.write_all(text.as_bytes())?;
-        wrt.flush()?;
-
+    fn log_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let scroll = &mut self.data.log_scroll;
+        match input {
+            Input { key: Key::Esc, .. }      => { self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }       => { *scroll = scroll.saturating_sub(1); },
+            Input { key: Key::Down, .. }     => { *scroll = scroll.saturating_add(1); },
+            Input { key: Key::PageUp, .. }   => { *scroll = scroll.saturating_sub(10); },
+            Input { key: Key::PageDown, .. } => { *scroll = scroll.saturating_add(10); },
+            _ => { /* ignore */ }
+        }
         Ok(())
     }
-
-    fn load(&mut self, x: u32) -> Result<()> {
-        *self.data.curr.value_mut() = format!("{x:03}");
-        let cwd = PathBuf::from_str(self.data.cwd.value()).unwrap();
-        let fname = cwd.join(format!("{x:03}.txt"));
-        let file = File::open(fname)?;
-        let file = BufReader::new(file);
-
-        self.data.text = textarea(file.lines().map(|s| s.unwrap()).collect(), self.data.srch.value());
-
+    fn keys_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let scroll = &mut self.data.keys_scroll;
+        match input {
+            Input { key: Key::Esc, .. }       => { self.set_mode(Mode::Command); },
+            Input { key: Key::Char('?'), .. } => { self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }        => { *scroll = scroll.saturating_sub(1); },
+            Input { key: Key::Down, .. }      => { *scroll = scroll.saturating_add(1); },
+            Input { key: Key::PageUp, .. }    => { *scroll = scroll.saturating_sub(10); },
+            Input { key: Key::PageDown, .. }  => { *scroll = scroll.saturating_add(10); },
+            _ => { /* ignore */ }
+        }
         Ok(())
     }
-
-    fn next(&mut self) -> Result<()> {
-        let curr: u32 = self.data.curr.value().parse()?;
-        self.load(curr + 1)
-    }
-    
-    fn prev(&mut self) -> Result<()> {
-        let curr: u32 = self.data.curr.value().parse()?;
-        self.load(curr - 1)
-    }
-
-    fn split_long_lines(&mut self) {
-        let text = self.data.text.lines().join("\n");
-        let text = LONG_LINES.replace_all(&text, "\n");
-        let text = text.lines().map(|s| s.to_owned()).collect();
-        self.data.text = textarea(text, self.data.srch.value());
+    fn flag_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let x = self.data.curr.value().parse()?;
+                self.data.flags.insert(x, self.data.note.value().to_string());
+                self.data.notify(format!("Flagged {}", self.id(x)));
+            },
+            Event::Key(event) => { self.data.note.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
     }
-}
-
-impl Data<'_> {
-    fn new() -> Self {
-        let cwd = current_dir().unwrap_or_default();
-        let default_search = r"\d+|f\.|fol|p\.|page|scan";
-        Self { 
-            mode: Mode::Command,
-            text: textarea(vec![], default_search),
-            cwd : TextState::new().with_value(cwd.to_string_lossy().to_string()),
-            curr: TextState::new().with_value("000"),
-            tot : 1,
-            srch: TextState::new().with_value(default_search),
-            msg : String::new(),
+    fn global_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let line = self.data.global.value().parse()?;
+                self.goto_global(line)?;
+            },
+            Event::Key(event) => { self.data.global.handle_key_event(event); },
+            _ => { /* ignore */}
         }
+        Ok(())
     }
-}
-impl View {
-    fn new() -> Self {
-        Self { }
+    fn goto_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            // gg goes to the top of the file
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Char('g'), ..}) if self.data.goto.value().is_empty() => {
+                self.set_mode(Mode::Command);
+                self.data.text.move_cursor(tui_textarea::CursorMove::Top);
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                match self.data.goto.value().trim().parse::<usize>() {
+                    Ok(line) => { self.goto_line(line); },
+                    Err(_) => { self.data.notify("The line must be a number"); },
+                }
+            },
+            Event::Key(event) => { self.data.goto.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
     }
-
-    fn ui(&mut self, data: &mut Data, frame: &mut Frame) {
-        let layout = Layout::vertical([
-            Constraint::Length(1),
-            Constraint::Min(0),
-            Constraint::Length(1),
-        ]).split(frame.size());
-
-        let title = Block::new()
-            .title_alignment(ratatui::layout::Alignment::Center)
-            .title(data.curr.value())
-            .add_modifier(Modifier::BOLD)
-            .set_style(Style::default().bg(Color::White).fg(Color::Blue));
-
-        let mode = Block::new()
-            .title_alignment(ratatui::layout::Alignment::Right)
-            .title(format!("{}", data.mode));
-
-        frame.render_widget(title, layout[0]);
-        frame.render_widget(data.text.widget(), layout[1]);
-
-        let status_line = Layout::horizontal([
-            Constraint::Min(0),
-            Constraint::Length(10)
-        ]).split(layout[2]);
-
-        frame.render_widget(mode, status_line[1]);
-
-        match data.mode {
-            Mode::OpenDir => {
-                TextPrompt::from("Open Directory")
-                    .draw(frame, status_line[0], &mut data.cwd);
+    fn replace_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
             },
-            Mode::OpenFile => {
-                TextPrompt::from("Open File (id only)")
-                    .draw(frame, status_line[0], &mut data.curr);
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, modifiers: KeyModifiers::SHIFT, ..}) |
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Tab, ..}) => { 
+                self.set_mode(Mode::Command);
+                self.replace_in_file();
             },
-            Mode::Search => {
-                TextPrompt::from("Search Pattern")
-                    .draw(frame, status_line[0], &mut data.srch);
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.replace_next();
             },
-            _ => {
-                if data.msg.is_empty() {
-                    let cur: u32 = data.curr.value().parse().unwrap();
-                    let ratio = (1 + cur) as f64 / (1 + data.tot) as f64;
-                    let progress = LineGauge::default().ratio(ratio);
-                    frame.render_widget(progress, status_line[0]);
-                } else {
-                    let msg = Block::new().title(data.msg.as_str())
-                        .style(Style::default().fg(Color::Red));
-                    frame.render_widget(msg, status_line[0]);
+            Event::Key(event) => { self.data.replacement.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn replace_pattern_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                match Regex::new(self.data.pages_pattern.value()) {
+                    Ok(_) => {
+                        self.set_mode(Mode::ReplacePages);
+                        self.data.replacement.move_end();
+                    },
+                    Err(e) => { self.data.notify(format!("Invalid pattern: {e}")); },
                 }
-            }
+            },
+            Event::Key(event) => { self.data.pages_pattern.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn replace_pages_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                self.ask_replace()?;
+            },
+            Event::Key(event) => { self.data.replacement.handle_key_event(event); },
+            _ => { /* ignore */}
         }
+        Ok(())
     }
-}
\ No newline at end of file
+    fn pad_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                match self.data.pad_width.value().trim().parse::<usize>() {
+                    Ok(pad @ 1..=9) => {
+                        self.set_mode(Mode::Command);
+                        self.data.pad = pad;
+                        if !self.resume()? {
+                            if let Some(x) = self.first_page()? {
+                                self.load(x)?;
+                            }
+                        }
+                    },
+                    _ => { self.data.notify("The width must be a number of digits (1 to 9)"); },
+                }
+            },
+            Event::Key(event) => { self.data.pad_width.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn store_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let name = self.data.register.value().trim().to_string();
+                if name.is_empty() {
+                    self.data.notify("A register needs a name");
+                } else {
+                    let text = std::mem::take(&mut self.data.stash);
+                    let count = text.lines().count();
+                    self.data.registers.insert(name.clone(), text);
+                    self.data.notify(format!("{count} lines stored in register {name}"));
+                }
+            },
+            Event::Key(event) => { self.data.register.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn recall_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let name = self.data.register.value().trim();
+                match self.data.registers.get(name).cloned() {
+                    Some(text) => {
+                        self.data.text.insert_str(text);
+                        self.modified();
+                    },
+                    None => { self.data.notify(format!("No register named {name}")); },
+                }
+            },
+            Event::Key(event) => { self.data.register.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn cuts_input(&mut self, input: Event) -> Result<()> {
+        if let Some(input) = self.movement(input) {
+            let input = input.into();
+            match input {
+                Input { key: Key::Esc, .. }       => { self.set_mode(Mode::Command); },
+                Input { key: Key::Char('+'), .. } => { self.data.min_run += 1; },
+                Input { key: Key::Char('-'), .. } => { self.data.min_run = self.data.min_run.saturating_sub(1).max(1); },
+                Input { key: Key::Enter, .. }     => { self.set_mode(Mode::Command); self.split_long_lines()?; },
+                _ => { /* ignore */ }
+            }
+        }
+        Ok(())
+    }
+    fn reflow_input(&mut self, input: Event) -> Result<()> {
+        if let Some(input) = self.movement(input) {
+            let input = input.into();
+            match input {
+                Input { key: Key::Esc, .. }       => { self.set_mode(Mode::Command); },
+                Input { key: Key::Char('+'), .. } => { self.data.reflow_width += 1; },
+                Input { key: Key::Char('-'), .. } => { self.data.reflow_width = self.data.reflow_width.saturating_sub(1).max(1); },
+                Input { key: Key::Enter, .. }     => { self.set_mode(Mode::Command); self.reflow_paragraph(); },
+                _ => { /* ignore */ }
+            }
+        }
+        Ok(())
+    }
+    fn diff_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let scroll = &mut self.data.changes_scroll;
+        match input {
+            Input { key: Key::Esc, .. }      => { self.data.changes.clear(); self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }       => { *scroll = scroll.saturating_sub(1); },
+            Input { key: Key::Down, .. }     => { *scroll = scroll.saturating_add(1); },
+            Input { key: Key::PageUp, .. }   => { *scroll = scroll.saturating_sub(10); },
+            Input { key: Key::PageDown, .. } => { *scroll = scroll.saturating_add(10); },
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+    fn hex_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let rows = self.data.hex.len().div_ceil(16) as u16;
+        let scroll = &mut self.data.hex_scroll;
+        match input {
+            Input { key: Key::Esc, .. }      => { self.data.hex.clear(); self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }       => { *scroll = scroll.saturating_sub(1); },
+            Input { key: Key::Down, .. }     => { *scroll = scroll.saturating_add(1).min(rows.saturating_sub(1)); },
+            Input { key: Key::PageUp, .. }   => { *scroll = scroll.saturating_sub(10); },
+            Input { key: Key::PageDown, .. } => { *scroll = scroll.saturating_add(10).min(rows.saturating_sub(1)); },
+            Input { key: Key::Home, .. }     => { *scroll = 0; },
+            Input { key: Key::End, .. }      => { *scroll = rows.saturating_sub(1); },
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+    fn message_input(&mut self, input: Event) -> Result<()> {
+        if let Event::Key(_) = input {
+            self.set_mode(Mode::Command);
+        }
+        Ok(())
+    }
+    fn chars_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let count = self.data.config.special_chars.len();
+        let selected = self.data.chars.selected().unwrap_or_default();
+        match input {
+            Input { key: Key::Esc, .. }  => { self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }   => { self.data.chars.select(Some(selected.saturating_sub(1))); },
+            Input { key: Key::Down, .. } => { self.data.chars.select(Some((selected + 1).min(count.saturating_sub(1)))); },
+            Input { key: Key::Enter, .. } => {
+                if let Some(c) = self.data.config.special_chars.get(selected) {
+                    self.data.text.insert_str(c);
+                    self.modified();
+                }
+                self.set_mode(Mode::Command);
+            },
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+    fn palette_input(&mut self, input: Event) -> Result<()> {
+        let commands = self.data.commands();
+        let selected = self.data.palette_state.selected().unwrap_or_default();
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Up, ..}) => { 
+                self.data.palette_state.select(Some(selected.saturating_sub(1)));
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Down, ..}) => { 
+                self.data.palette_state.select(Some((selected + 1).min(commands.len().saturating_sub(1))));
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                if let Some(Ok(key)) = commands.get(selected).map(|(key, _, _)| key.parse::<keys::KeyBinding>()) {
+                    self.command_input(key.press())?;
+                }
+            },
+            Event::Key(event) => {
+                self.data.palette.handle_key_event(event);
+                self.data.palette_state.select(Some(0));
+            },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn recent_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let count = self.data.recent.len();
+        let selected = self.data.recent_state.selected().unwrap_or_default();
+        match input {
+            Input { key: Key::Esc, .. }  => { self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }   => { self.data.recent_state.select(Some(selected.saturating_sub(1))); },
+            Input { key: Key::Down, .. } => { self.data.recent_state.select(Some((selected + 1).min(count.saturating_sub(1)))); },
+            Input { key: Key::Enter, .. } => {
+                self.set_mode(Mode::Command);
+                if let Some(dir) = self.data.recent.get(selected).cloned() {
+                    *self.data.cwd.value_mut() = dir;
+                    self.data.cwd.move_end();
+                    self.open()?;
+                }
+            },
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+    fn focus_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let count = self.data.focus.len();
+        let selected = self.data.focus_state.selected().unwrap_or_default();
+        match input {
+            Input { key: Key::Esc, .. }  => { self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }   => { self.data.focus_state.select(Some(selected.saturating_sub(1))); },
+            Input { key: Key::Down, .. } => { self.data.focus_state.select(Some((selected + 1).min(count.saturating_sub(1)))); },
+            Input { key: Key::Enter, .. } => {
+                if let Some(row) = self.data.focus.get(selected) {
+                    self.data.text.move_cursor(tui_textarea::CursorMove::Jump((*row).min(u16::MAX as usize) as u16, 0));
+                }
+                self.set_mode(Mode::Command);
+            },
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+    fn compare_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                let ids = self.data.cmp.value().split_whitespace()
+                    .map(|id| id.parse())
+                    .collect::<std::result::Result<Vec<u32>, _>>()?;
+                let [l, r] = ids[..] else {
+                    self.data.notify("Two file ids are expected");
+                    return Ok(());
+                };
+                self.set_mode(Mode::Command);
+                let left  = (l, self.read(l)?);
+                let right = (r, self.read(r)?);
+                self.data.compare = Some(Compare { left, right, scroll: 0 });
+                self.set_mode(Mode::SideBySide);
+            },
+            Event::Key(event) => { self.data.cmp.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn pane_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let x: u32 = self.data.pane_id.value().parse()?;
+                if self.data.curr.value().parse() == Ok(x) {
+                    self.data.notify("The file is already open in this pane");
+                    return Ok(());
+                }
+                let text = textarea(self.read(x)?, &self.data.search());
+                self.data.pane = Some(Pane { x, text, dirty: false, first: false, stacked: false });
+            },
+            Event::Key(event) => { self.data.pane_id.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn new_page_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let x = self.data.new_page.value().trim().parse()?;
+                self.new_page(x)?;
+            },
+            Event::Key(event) => { self.data.new_page.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn save_as_input(&mut self, input: Event) -> Result<()> {
+        match input {
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Esc, ..}) => { 
+                self.set_mode(Mode::Command); 
+            },
+            Event::Key(KeyEvent{kind: crossterm::event::KeyEventKind::Press, code: KeyCode::Enter, ..}) => { 
+                self.set_mode(Mode::Command);
+                let x = self.data.save_as.value().parse()?;
+                let fname = self.fname(x);
+                if fname.exists() {
+                    self.confirm(format!("Overwrite {} ?", fname.display()), Pending::SaveAs(x));
+                } else {
+                    self.save_as(x)?;
+                }
+            },
+            Event::Key(event) => { self.data.save_as.handle_key_event(event); },
+            _ => { /* ignore */}
+        }
+        Ok(())
+    }
+    fn side_by_side_input(&mut self, input: Event) -> Result<()> {
+        let input = input.into();
+        let Some(compare) = self.data.compare.as_mut() else {
+            self.set_mode(Mode::Command);
+            return Ok(());
+        };
+        let scroll = &mut compare.scroll;
+        match input {
+            Input { key: Key::Esc, .. }      => { self.data.compare = None; self.set_mode(Mode::Command); },
+            Input { key: Key::Up, .. }       => { *scroll = scroll.saturating_sub(1); },
+            Input { key: Key::Down, .. }     => { *scroll = scroll.saturating_add(1); },
+            Input { key: Key::PageUp, .. }   => { *scroll = scroll.saturating_sub(10); },
+            Input { key: Key::PageDown, .. } => { *scroll = scroll.saturating_add(10); },
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+    /// Opens a second file next to the current one, or closes the other
+    /// pane when it is open (and saved)
+    fn toggle_pane(&mut self) {
+        match self.data.pane.as_ref() {
+            Some(pane) if pane.dirty => {
+                self.data.notify(format!("{} has unsaved changes, switch to it (ctrl+w) and save it first", self.id(pane.x)));
+            },
+            Some(_) => { self.data.pane = None; },
+            None if self.data.in_scratch => { self.data.notify("The scratch buffer cannot be split"); },
+            None if self.data.path.is_some() => { self.data.notify("Only the pages can be split"); },
+            None => {
+                self.set_mode(Mode::Pane);
+                self.data.pane_id.move_end();
+            },
+        }
+    }
+    /// Splits the view between the current page (at the top) and the next
+    /// one (at the bottom), or closes the split view
+    fn split_adjacent(&mut self) -> Result<()> {
+        if self.data.pane.is_some() || self.data.in_scratch || self.data.path.is_some() {
+            self.toggle_pane();
+            return Ok(());
+        }
+        let curr: u32 = self.data.curr.value().parse()?;
+        let next = curr + 1;
+        if !self.fname(next).is_file() {
+            self.data.notify(format!("There is no page {} after this one", self.id(next)));
+            return Ok(());
+        }
+        let text = textarea(self.read(next)?, &self.data.search());
+        self.data.pane = Some(Pane { x: next, text, dirty: false, first: false, stacked: true });
+        Ok(())
+    }
+    /// Moves the focus to the other pane of the split view
+    fn switch_pane(&mut self) {
+        if self.data.in_scratch {
+            self.data.notify("Leave the scratch buffer first");
+            return;
+        }
+        let Some(pane) = self.data.pane.as_mut() else {
+            self.data.notify("No split view");
+            return;
+        };
+        let Ok(x) = self.data.curr.value().parse() else {
+            return;
+        };
+        std::mem::swap(&mut self.data.text, &mut pane.text);
+        std::mem::swap(&mut self.data.dirty, &mut pane.dirty);
+        let other = std::mem::replace(&mut pane.x, x);
+        pane.first = !pane.first;
+        // the text cut in one file can be pasted in the other
+        let yank = pane.text.yank_text();
+        self.data.text.set_yank_text(yank);
+        *self.data.curr.value_mut() = self.id(other);
+        self.check_line_length();
+        self.data.counts = None;
+    }
+    fn quit_input(&mut self, _input: Event) -> Result<()> {
+        Ok(())
+    }
+
+    fn quit(&mut self) -> Result<()> {
+        self.store_scratch()?;
+        self.set_mode(Mode::Quit);
+        Ok(())
+    }
+
+    /// Asks the user to confirm the action before it gets performed
+    fn confirm(&mut self, question: String, action: Pending) {
+        let deadline = self.data.config.confirm_timeout
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        self.data.confirm = Some(Confirm { question, action, deadline });
+        self.set_mode(Mode::Confirm);
+    }
+
+    /// Undoes what was prepared for an action which is not confirmed
+    fn abandon(&mut self, action: Pending) {
+        if let Pending::Open(_) = action {
+            // the pages still come from the directory opened before
+            *self.data.cwd.value_mut() = self.data.opened.clone();
+        }
+    }
+
+    /// Performs an action once it has been confirmed
+    fn perform(&mut self, action: Pending) -> Result<()> {
+        match action {
+            Pending::Export    => self.write_export(),
+            Pending::Markdown  => self.write_markdown(),
+            Pending::SaveAs(x) => self.save_as(x),
+            Pending::Split     => self.split_at_headings(),
+            Pending::Quit      => self.quit(),
+            Pending::Load(x)   => { self.data.dirty = false; self.load(x) },
+            Pending::LoadPath(path) => { self.data.dirty = false; self.load_path(path) },
+            Pending::Open(tot) => self.open_dir(tot, false),
+            Pending::Replace(pattern, replacement) => self.replace_all(pattern, replacement),
+            Pending::Chapters(moves) => {
+                chapters::apply(&moves)?;
+                self.data.notify(format!("Moved {} files into their chapters", moves.len()));
+                self.data.tot = self.count()?;
+                self.load_first()
+            },
+            Pending::Columns(rows, table) => {
+                let lines = self.data.text.lines();
+                let text = [&lines[..rows.start], &table, &lines[rows.end..]].concat();
+                self.rewrite(text, (rows.start, 0));
+                Ok(())
+            },
+        }
+    }
+
+    /// How long to wait for an event before something must happen anyway
+    /// (None means the app can block until the next event)
+    fn timeout(&self) -> Option<Duration> {
+        let confirm = self.data.confirm.as_ref().and_then(|c| c.deadline);
+        [confirm, self.data.next_reload, self.data.next_autosave, self.data.flash].into_iter()
+            .flatten()
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Handles the expiry of the timers when no event arrived in time
+    fn tick(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let expired = self.data.confirm.as_ref()
+            .and_then(|c| c.deadline)
+            .is_some_and(|deadline| deadline <= now);
+        if expired {
+            if let Some(confirm) = self.data.confirm.take() {
+                self.abandon(confirm.action);
+            }
+            self.set_mode(Mode::Command);
+            self.data.notify("Confirmation timed out");
+        }
+        if self.data.flash.is_some_and(|deadline| deadline <= now) {
+            self.data.flash = None;
+        }
+        if self.data.next_reload.is_some_and(|deadline| deadline <= now) {
+            self.data.next_reload = self.data.config.reload_interval
+                .map(|secs| now + Duration::from_secs(secs));
+            self.reload()?;
+        }
+        if self.data.next_autosave.is_some_and(|deadline| deadline <= now) {
+            self.data.next_autosave = self.data.config.autosave
+                .map(|secs| now + Duration::from_secs(secs));
+            self.autosave()?;
+        }
+        Ok(())
+    }
+
+    /// The number of pages in the current directory (the scans, exports
+    /// and other files are not counted)
+    fn count(&self) -> Result<usize> {
+        Ok(self.page_names()?.len())
+    }
+    /// Opens the current directory at its lowest-numbered page (if it has
+    /// any)
+    fn load_first(&mut self) -> Result<()> {
+        if let Some(x) = self.first_page()? {
+            self.data.tot = self.count()?;
+            self.data.todo = self.untranscribed()?;
+            self.restore_view();
+            if !self.detect_pad()? {
+                self.load(x)?;
+            }
+        }
+        Ok(())
+    }
+    /// The lowest-numbered page of the current directory (if it has any)
+    fn first_page(&self) -> Result<Option<u32>> {
+        Ok(self.page_names()?.iter().filter_map(|name| name.parse().ok()).min())
+    }
+    /// The names (without their extension) of the pages of the current
+    /// directory
+    fn page_names(&self) -> Result<Vec<String>> {
+        Ok(read_dir(self.data.cwd.value())?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "txt"))
+            .filter_map(|p| p.file_stem()?.to_str().map(|s| s.to_string()))
+            .filter(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
+            .collect())
+    }
+    /// The ids of the pages of the current directory, in increasing order
+    fn page_ids(&self) -> Result<Vec<u32>> {
+        let mut ids = self.page_names()?.iter()
+            .filter_map(|name| name.parse().ok())
+            .collect::<Vec<u32>>();
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+    /// Detects the number of digits of the page names of the current
+    /// directory (unless it is configured). A name starting with a zero is
+    /// padded to its exact width, while the other ones only need to be at
+    /// least as wide. When the names mix several widths (e.g. 01.txt and
+    /// 001.txt) the user is asked to choose one, and true is returned.
+    fn detect_pad(&mut self) -> Result<bool> {
+        if let Some(pad) = self.data.config.pad {
+            self.data.pad = pad;
+            return Ok(false);
+        }
+        let names = self.page_names()?;
+        let mut widths = BTreeMap::new();
+        let mut padded = BTreeMap::new();
+        for name in names.iter() {
+            *widths.entry(name.len()).or_insert(0) += 1;
+            if name.len() > 1 && name.starts_with('0') {
+                *padded.entry(name.len()).or_insert(0) += 1;
+            }
+        }
+        let shortest = names.iter()
+            .filter(|name| name.len() == 1 || !name.starts_with('0'))
+            .map(|name| name.len())
+            .min();
+        let detected = match padded.keys().collect::<Vec<_>>()[..] {
+            []      => shortest,
+            [width] => shortest.is_none_or(|s| s >= *width).then_some(*width),
+            _       => None,
+        };
+        match detected {
+            Some(pad) => { self.data.pad = pad; },
+            None if names.is_empty() => { /* nothing to detect */ },
+            None => {
+                let mix = widths.iter()
+                    .map(|(width, count)| format!("{width} digits: {count} pages"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.data.notify(format!("The page names mix several widths ({mix}), please choose one"));
+                let common = padded.iter().max_by_key(|(_, count)| **count).map_or(self.data.pad, |(width, _)| *width);
+                *self.data.pad_width.value_mut() = common.to_string();
+                self.data.pad_width.move_end();
+                self.set_mode(Mode::Pad);
+                return Ok(true);
+            },
+        }
+        Ok(false)
+    }
+    /// The id of the given page as it appears in its file name
+    fn id(&self, x: u32) -> String {
+        format!("{x:0pad$}", pad = self.data.pad)
+    }
+    /// Restores the view settings (and the highlight pattern) that were
+    /// chosen for the current directory
+    fn restore_view(&mut self) {
+        match Session::load(Path::new(self.data.cwd.value())) {
+            Ok(session) => {
+                self.data.view = session.view.unwrap_or(self.data.config.view);
+                self.data.highlight = session.highlight.unwrap_or_else(|| self.data.config.highlight.clone());
+            },
+            Err(e) => {
+                self.data.view = self.data.config.view;
+                self.data.highlight = self.data.config.highlight.clone();
+                self.data.notify(format!("cannot restore the view settings: {e}"));
+            },
+        }
+    }
+    /// Switches the gauge between the collection and the current file
+    fn toggle_gauge(&mut self) {
+        self.data.gauge = match self.data.gauge {
+            Gauge::Collection => Gauge::File,
+            Gauge::File       => Gauge::Collection,
+        };
+    }
+    /// The number of lines of a page. It is only read again from the disk
+    /// when the file was modified since it was last counted
+    fn line_count(&mut self, x: u32) -> Result<usize> {
+        let fname = self.fname(x);
+        let Ok(modified) = std::fs::metadata(&fname).and_then(|m| m.modified()) else {
+            return Ok(0);
+        };
+        if let Some((when, count)) = self.data.line_counts.get(&x) {
+            if *when == modified {
+                return Ok(*count);
+            }
+        }
+        let count = String::from_utf8_lossy(&std::fs::read(&fname)?).lines().count();
+        self.data.line_counts.insert(x, (modified, count));
+        Ok(count)
+    }
+    /// Lists where each page would be moved according to the chapter
+    /// definitions (dry run), then asks for a confirmation before moving them
+    fn ask_chapters(&mut self) -> Result<()> {
+        let moves = chapters::plan(Path::new(self.data.cwd.value()), &self.data.config.chapters, self.data.pad)?;
+        if moves.is_empty() {
+            self.data.notify("No page belongs to a chapter");
+            return Ok(());
+        }
+        for m in moves.iter() {
+            self.data.notify(format!("{} -> {}", m.from.display(), m.to.display()));
+        }
+        self.confirm(
+            format!("Move {} files into their chapters (this restructures the directory) ?", moves.len()),
+            Pending::Chapters(moves));
+        Ok(())
+    }
+    /// Replaces the match of the search pattern under (or after) the cursor,
+    /// then moves to the next one
+    fn replace_next(&mut self) {
+        let Some(pattern) = self.data.text.search_pattern().cloned() else {
+            self.data.notify("No search pattern");
+            return;
+        };
+        if !self.data.text.search_forward(true) {
+            self.data.notify("No match left");
+            return;
+        }
+        let (row, col) = self.data.text.cursor();
+        let mut lines = self.data.text.lines().to_vec();
+        let line = &lines[row];
+        let at = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+        let Some(caps) = pattern.captures_at(line, at) else {
+            return;
+        };
+        let m = caps.get(0).unwrap();
+        let mut with = String::new();
+        caps.expand(self.data.replacement.value(), &mut with);
+        let end = col + with.chars().count();
+        lines[row] = format!("{}{with}{}", &line[..m.start()], &line[m.end()..]);
+        self.rewrite(lines, (row, end));
+        if !self.data.text.search_forward(true) {
+            self.data.notify("Replaced the last match");
+        }
+    }
+    /// Replaces all the matches of the search pattern in the buffer
+    fn replace_in_file(&mut self) {
+        let Some(pattern) = self.data.text.search_pattern().cloned() else {
+            self.data.notify("No search pattern");
+            return;
+        };
+        let replacement = self.data.replacement.value();
+        let mut count = 0;
+        let lines = self.data.text.lines().iter()
+            .map(|line| {
+                count += pattern.find_iter(line).count();
+                pattern.replace_all(line, replacement).to_string()
+            })
+            .collect();
+        if count > 0 {
+            let cursor = self.data.text.cursor();
+            self.rewrite(lines, cursor);
+        }
+        self.data.notify(format!("{count} matches replaced"));
+    }
+    /// Lists the number of matches of the search pattern in each page (dry
+    /// run), then asks for a confirmation before replacing them all
+    fn ask_replace(&mut self) -> Result<()> {
+        let pattern = Regex::new(self.data.pages_pattern.value())?;
+        let plan = replace::plan(Path::new(self.data.cwd.value()), &pattern)?;
+        if plan.is_empty() {
+            self.data.notify("No match in the pages");
+            return Ok(());
+        }
+        for (page, count) in plan.iter() {
+            self.data.notify(format!("{}: {count} matches", page.display()));
+        }
+        let total = plan.iter().map(|(_, count)| count).sum::<usize>();
+        let replacement = self.data.replacement.value().to_string();
+        self.confirm(
+            format!("Replace {total} matches in {} files ?", plan.len()),
+            Pending::Replace(pattern, replacement));
+        Ok(())
+    }
+    /// Replaces the matches of the pattern in all the pages, then reloads
+    /// the current one (which is saved first so that no edit gets lost)
+    fn replace_all(&mut self, pattern: Regex, replacement: String) -> Result<()> {
+        if self.data.dirty && !self.data.in_scratch {
+            self.save()?;
+        }
+        let cwd = PathBuf::from(self.data.cwd.value());
+        let done = replace::apply(&cwd, &pattern, &replacement, self.data.config.backup)?;
+        let total = done.iter().map(|(_, count)| count).sum::<usize>();
+        if let Some(path) = self.data.path.clone() {
+            self.load_path(path)?;
+        } else if !self.data.in_scratch {
+            let x = self.data.curr.value().parse()?;
+            self.load(x)?;
+        }
+        self.data.notify(format!("Replaced {total} matches in {} files", done.len()));
+        Ok(())
+    }
+    /// Goes to the first empty (or blank) line of the file, which is where a
+    /// partial transcription left off, or to its end when there is none
+    fn goto_empty_line(&mut self) {
+        match self.data.text.lines().iter().position(|l| l.trim().is_empty()) {
+            Some(row) => {
+                self.data.text.move_cursor(tui_textarea::CursorMove::Jump(row.min(u16::MAX as usize) as u16, 0));
+            },
+            None => {
+                self.data.text.move_cursor(tui_textarea::CursorMove::Bottom);
+                self.data.text.move_cursor(tui_textarea::CursorMove::End);
+                self.data.notify("No empty line");
+            },
+        }
+    }
+    /// Goes to the given line (1-based) of the file, or to its last line when
+    /// it is shorter than that
+    fn goto_line(&mut self, line: usize) {
+        let count = self.data.text.lines().len();
+        if line > count {
+            self.data.notify(format!("The file only has {count} lines"));
+        }
+        let row = line.clamp(1, count) - 1;
+        self.data.text.move_cursor(tui_textarea::CursorMove::Jump(row.min(u16::MAX as usize) as u16, 0));
+    }
+    /// Goes to the given line (1-based) of the collection seen as one long
+    /// document made of all its pages
+    fn goto_global(&mut self, line: usize) -> Result<()> {
+        let mut offset = line.max(1);
+        for x in self.page_ids()? {
+            let count = self.line_count(x)?;
+            if offset <= count {
+                self.load(x)?;
+                self.data.text.move_cursor(tui_textarea::CursorMove::Jump((offset - 1).min(u16::MAX as usize) as u16, 0));
+                return Ok(());
+            }
+            offset -= count;
+        }
+        self.data.notify(format!("The collection only has {} lines", line.max(1) - offset));
+        Ok(())
+    }
+    /// Reports whether every page of the collection (from its first page to
+    /// its last one) exists and holds at least the configured number of
+    /// characters
+    fn check_complete(&mut self) -> Result<()> {
+        let min = self.data.config.min_chars;
+        let ids = self.page_ids()?;
+        let (Some(first), Some(last)) = (ids.first().copied(), ids.last().copied()) else {
+            self.data.notify("The directory holds no page");
+            return Ok(());
+        };
+        let total = last - first + 1;
+        let missing = (first..=last)
+            .filter(|x| std::fs::read(self.fname(*x)).map_or(true, |bytes| {
+                String::from_utf8_lossy(&bytes).chars().filter(|c| !c.is_whitespace()).count() < min
+            }))
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            self.data.notify(format!("Complete: all {total} pages are transcribed"));
+        } else {
+            let list = missing.iter().take(10).map(|x| self.id(*x)).collect::<Vec<_>>().join(", ");
+            let more = if missing.len() > 10 { ", ..." } else { "" };
+            self.data.notify(format!("Incomplete: {} pages missing or too short ({list}{more})", missing.len()));
+        }
+        Ok(())
+    }
+    /// Checks all the files of the directory and lists the problems found in
+    /// the message log
+    fn lint(&mut self) -> Result<()> {
+        let problems = lint::lint(Path::new(self.data.cwd.value()))?;
+        if problems.is_empty() {
+            self.data.notify("All files decode cleanly");
+            return Ok(());
+        }
+        let count = problems.len();
+        for problem in problems {
+            self.data.notify(problem);
+        }
+        self.data.notify(format!("{count} problems found"));
+        self.set_mode(Mode::Log);
+        self.data.log_scroll = 0;
+        Ok(())
+    }
+    /// Reports the configured key bindings which shadow other keys (in the
+    /// message log, which is opened when asked to)
+    fn report_conflicts(&mut self, show: bool) {
+        let cycle = &self.data.config.cycle;
+        let conflicts = keys::conflicts(&[
+            ("cycle.forward", cycle.forward),
+            ("cycle.backward", cycle.backward),
+        ], &self.data.keymap);
+        if conflicts.is_empty() {
+            if show {
+                self.data.notify("No key binding conflict");
+            }
+            return;
+        }
+        let count = conflicts.len();
+        for conflict in conflicts {
+            self.data.notify(conflict);
+        }
+        self.data.notify(format!("{count} key binding conflicts (see the messages)"));
+        if show {
+            self.set_mode(Mode::Log);
+            self.data.log_scroll = 0;
+        }
+    }
+    /// Starts (or stops) measuring the time it takes to handle an event and
+    /// redraw the screen
+    fn toggle_latency(&mut self) {
+        if self.data.latency.take().is_some() {
+            self.data.notify("Latency no longer measured");
+        } else {
+            self.data.latency = Some(VecDeque::new());
+            self.data.notify("Measuring the latency");
+        }
+    }
+    /// Reads the key map, reporting the names which are not those of an
+    /// action. The default keys are used when it cannot be read.
+    fn load_keymap(&mut self) {
+        match keys::KeyMap::load() {
+            Ok((keymap, problems)) => {
+                self.data.keymap = keymap;
+                for problem in problems {
+                    self.data.notify(problem);
+                }
+            },
+            Err(e) => {
+                self.data.keymap = keys::KeyMap::default();
+                self.data.notify(format!("cannot read keys.toml: {e}"));
+            },
+        }
+    }
+    /// Reads the configuration again and applies it right away. The settings
+    /// toggled at runtime are kept (Z resets them to the new defaults)
+    fn reload_config(&mut self) {
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                self.data.notify(format!("{e}"));
+                return;
+            },
+        };
+        if self.data.highlight == self.data.config.highlight {
+            self.data.highlight = config.highlight.clone();
+        }
+        if self.data.srch.value() == self.data.config.highlight {
+            *self.data.srch.value_mut() = config.highlight.clone();
+            self.data.srch.move_end();
+            self.data.text.set_search_pattern(self.data.search()).ok();
+        }
+        self.data.clipboard = config.clipboard.open();
+        self.data.next_reload = config.reload_interval
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        self.data.next_autosave = config.autosave
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        self.data.config = config;
+        self.data.notify("Configuration reloaded");
+        self.load_keymap();
+        self.report_conflicts(false);
+    }
+    /// Resets the settings toggled at runtime to their configured defaults,
+    /// or to their initial state for those which are not configured
+    fn reset_settings(&mut self) -> Result<()> {
+        self.data.view    = self.data.config.view;
+        self.data.gauge   = self.data.config.gauge;
+        self.data.min_run = self.data.config.split.min_run;
+        self.data.reflow_width = self.data.config.reflow_width;
+        self.data.zen     = self.data.config.distraction_free;
+        self.data.overwrite   = false;
+        self.data.search_ci   = false;
+        self.data.search_word = false;
+        self.data.text.set_search_pattern(self.data.search()).ok();
+        self.data.show_scan = false;
+        self.data.scan      = None;
+        self.store_view()?;
+        self.data.notify("Settings reset to their defaults");
+        Ok(())
+    }
+    /// Remembers the view settings for the current directory
+    fn store_view(&mut self) -> Result<()> {
+        let cwd = Path::new(self.data.cwd.value());
+        let mut session = Session::load(cwd).unwrap_or_default();
+        session.view = Some(self.data.view);
+        session.save(cwd)
+    }
+    /// Remembers the current page and the position of the cursor for the
+    /// current directory, so that they are restored when it is opened again
+    fn store_position(&mut self) -> Result<()> {
+        if self.data.in_scratch || self.data.path.is_some() {
+            return Ok(());
+        }
+        let cwd = Path::new(self.data.cwd.value());
+        let mut session = Session::load(cwd).unwrap_or_default();
+        session.page = self.data.curr.value().parse().ok();
+        session.cursor = Some(self.data.text.cursor());
+        session.save(cwd)
+    }
+    /// Opens the page that was last edited in the current directory, and
+    /// puts the cursor back where it was. Returns false (and loads nothing)
+    /// when there is no such page, e.g. when it has been deleted since or
+    /// when the state file cannot be read.
+    fn resume(&mut self) -> Result<bool> {
+        let session = Session::load(Path::new(self.data.cwd.value())).unwrap_or_default();
+        match session.page {
+            Some(x) if self.fname(x).is_file() => {
+                self.load(x)?;
+                if let Some((row, col)) = session.cursor {
+                    let row = row.min(u16::MAX as usize) as u16;
+                    let col = col.min(u16::MAX as usize) as u16;
+                    self.data.text.move_cursor(tui_textarea::CursorMove::Jump(row, col));
+                }
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+
+    /// Counts the pages of the directory again in case they were changed by
+    /// some other process
+    fn reload(&mut self) -> Result<()> {
+        if self.data.lazy {
+            return Ok(());
+        }
+        let tot = self.count()?;
+        if tot > self.data.tot {
+            self.data.notify(format!("{} new pages in the directory", tot - self.data.tot));
+        }
+        self.data.tot = tot;
+        self.data.todo = self.untranscribed()?;
+        Ok(())
+    }
+
+    fn fname(&self, x: u32) -> PathBuf {
+        let cwd = PathBuf::from_str(self.data.cwd.value()).unwrap();
+        cwd.join(format!("{}.txt", self.id(x)))
+    }
+    /// The file the buffer is saved to: the file opened by its path if any,
+    /// the current page otherwise
+    fn buffer_fname(&self) -> Result<PathBuf> {
+        match self.data.path.clone() {
+            Some(path) => Ok(path),
+            None       => Ok(self.fname(self.data.curr.value().parse()?)),
+        }
+    }
+
+    /// Reads the lines of a file without loading it in the editor
+    fn read(&self, x: u32) -> Result<Vec<String>> {
+        let bytes = std::fs::read(self.fname(x))?;
+        Ok(decode(&bytes, self.data.config.normalize_newlines).0)
+    }
+
+    fn save(&mut self) -> Result<()> {
+        if self.data.in_scratch {
+            return self.store_scratch();
+        }
+        if self.data.config.trim_on_save {
+            self.trim();
+        }
+        if let Some(path) = self.data.path.clone() {
+            self.write(&path)?;
+            self.data.dirty = false;
+            return Ok(());
+        }
+        let x: u32 = self.data.curr.value().parse()?;
+        let fname = self.fname(x);
+        if self.data.text.lines().iter().all(|l| l.is_empty()) {
+            match self.data.config.empty_save {
+                EmptySave::Write  => { /* an empty page is saved as such */ },
+                EmptySave::Delete => {
+                    if fname.exists() {
+                        std::fs::remove_file(&fname)?;
+                    }
+                    self.data.dirty = false;
+                    self.data.tot = self.count()?;
+                    self.update_todo(x);
+                    self.data.notify(format!("Deleted {} (empty buffer)", fname.display()));
+                    return Ok(());
+                },
+                EmptySave::Refuse => {
+                    self.data.notify("The buffer is empty, it was not saved");
+                    return Ok(());
+                },
+            }
+        }
+        self.write(&fname)?;
+        self.data.dirty = false;
+        self.store_position()?;
+        self.check_line_length();
+        self.update_todo(x);
+
+        Ok(())
+    }
+
+    /// Saves the buffer if it has unsaved changes. Unlike an explicit save,
+    /// this never trims the buffer (the user may well be typing) and leaves
+    /// an empty buffer alone rather than deleting or refusing the page.
+    fn autosave(&mut self) -> Result<()> {
+        if !self.data.dirty || self.data.in_scratch || self.data.text.lines().iter().all(|l| l.is_empty()) {
+            return Ok(());
+        }
+        let fname = self.buffer_fname()?;
+        self.write(&fname)?;
+        self.data.dirty = false;
+        if let (None, Ok(x)) = (self.data.path.as_ref(), self.data.curr.value().parse()) {
+            self.update_todo(x);
+        }
+        self.store_position()
+    }
+
+    /// Lists the changes of the buffer since its file was last saved (a file
+    /// which does not exist yet counts as empty)
+    fn show_changes(&mut self) -> Result<()> {
+        if self.data.in_scratch {
+            self.data.notify("The scratch buffer has no file to compare with");
+            return Ok(());
+        }
+        let fname = self.buffer_fname()?;
+        let saved = if fname.exists() {
+            decode(&std::fs::read(&fname)?, self.data.config.normalize_newlines).0
+        } else {
+            vec![]
+        };
+        let changes = diff(&saved, self.data.text.lines());
+        if changes.iter().all(|c| matches!(c, Change::Same(_))) {
+            self.data.notify(format!("No change since {} was saved", fname.display()));
+            return Ok(());
+        }
+        self.data.changes = changes;
+        self.data.changes_scroll = 0;
+        self.set_mode(Mode::Diff);
+        Ok(())
+    }
+
+    /// Removes the trailing whitespace of the lines and the trailing blank
+    /// lines of the buffer (leaving it untouched when there is none)
+    fn trim(&mut self) {
+        let lines = self.data.text.lines();
+        let mut trimmed = lines.iter()
+            .map(|l| l.trim_end().to_string())
+            .collect::<Vec<_>>();
+        while trimmed.len() > 1 && trimmed.last().is_some_and(|l| l.is_empty()) {
+            trimmed.pop();
+        }
+        if trimmed != lines {
+            let cursor = self.data.text.cursor();
+            self.rewrite(trimmed, cursor);
+        }
+    }
+
+    /// Writes the content of the buffer to the given file (atomically)
+    fn write(&self, fname: &Path) -> Result<()> {
+        let text = self.data.text.lines().join(self.data.eol);
+        files::write(fname, text.as_bytes())
+    }
+
+    /// Creates the (empty) page with the given id and opens it, unless the
+    /// page already exists
+    fn new_page(&mut self, x: u32) -> Result<()> {
+        let fname = self.fname(x);
+        if fname.exists() {
+            self.data.notify(format!("{} already exists, it was left untouched", fname.display()));
+            return Ok(());
+        }
+        files::write(&fname, b"")?;
+        self.data.tot = self.count()?;
+        self.load(x)?;
+        self.data.notify(format!("Created {}", fname.display()));
+        Ok(())
+    }
+
+    /// Writes the buffer to another file, without leaving the current one
+    fn save_as(&mut self, x: u32) -> Result<()> {
+        if self.data.in_scratch {
+            self.data.notify("The scratch buffer is never saved to the pages");
+            return Ok(());
+        }
+        let fname = self.fname(x);
+        self.write(&fname)?;
+        self.data.notify(format!("Saved to {}", fname.display()));
+        Ok(())
+    }
+
+    fn load(&mut self, x: u32) -> Result<()> {
+        // two buffers of the same file would overwrite each other's changes
+        if let Some(pane) = self.data.pane.as_ref().filter(|pane| pane.x == x) {
+            // the pages of the stacked split follow each other, moving from
+            // one to the other simply moves the focus
+            if pane.stacked {
+                self.switch_pane();
+                return Ok(());
+            }
+            self.data.notify(format!("{} is already open in the other pane, switch to it (ctrl+w)", self.id(x)));
+            return Ok(());
+        }
+        if self.data.dirty {
+            self.confirm(format!("Load {} (unsaved changes are lost) ?", self.id(x)), Pending::Load(x));
+            return Ok(());
+        }
+        if self.data.in_scratch {
+            self.toggle_scratch()?;
+        }
+        self.read_buffer(&self.fname(x), &self.id(x))?;
+        *self.data.curr.value_mut() = self.id(x);
+        self.data.path = None;
+        self.load_scan();
+        Ok(())
+    }
+
+    /// Reads the scanned image of the current page when the scans are
+    /// shown. The pane is hidden (with a message) when there is none.
+    fn load_scan(&mut self) {
+        self.data.scan = None;
+        if !self.data.show_scan || self.data.path.is_some() || self.data.in_scratch {
+            return;
+        }
+        let Ok(x) = self.data.curr.value().parse::<u32>() else { return };
+        match self.scan_path(x).map(Scan::open) {
+            Some(Ok(scan)) => { self.data.scan = Some(scan); },
+            Some(Err(e))   => { self.data.notify(format!("cannot show the scan of {}: {e}", self.id(x))); },
+            None           => { self.data.notify(format!("No scan of {}", self.id(x))); },
+        }
+    }
+
+    /// Opens a file which is not one of the pages. It gets saved back to
+    /// the same path.
+    fn load_path(&mut self, path: PathBuf) -> Result<()> {
+        if self.data.pane.is_some() {
+            self.data.notify("Close the split view (Y) before opening a file which is not a page");
+            return Ok(());
+        }
+        if self.data.dirty {
+            self.confirm(format!("Open {} (unsaved changes are lost) ?", path.display()), Pending::LoadPath(path));
+            return Ok(());
+        }
+        if self.data.in_scratch {
+            self.toggle_scratch()?;
+        }
+        self.read_buffer(&path, &path.display().to_string())?;
+        self.data.path = Some(path);
+        self.load_scan();
+        Ok(())
+    }
+
+    /// Reads the file (named `name` in the messages) into the buffer
+    fn read_buffer(&mut self, fname: &Path, name: &str) -> Result<()> {
+        let bytes = std::fs::read(fname)?;
+        if std::str::from_utf8(&bytes).is_err() {
+            self.data.notify(format!("{name} is not valid UTF-8, the invalid bytes are shown as \u{fffd}"));
+        }
+        let (lines, eol) = decode(&bytes, self.data.config.normalize_newlines);
+        self.data.eol = eol;
+
+        if !self.data.config.persistent_search {
+            *self.data.srch.value_mut() = self.data.highlight.clone();
+        }
+        self.data.text = textarea(lines, &self.data.search());
+        self.data.dirty = false;
+        self.data.counts = None;
+        self.check_line_length();
+
+        Ok(())
+    }
+
+    /// Updates the pages left to transcribe once the buffer was saved to the
+    /// given page, rather than reading all the pages again
+    fn update_todo(&mut self, x: u32) {
+        if self.data.lazy {
+            return;
+        }
+        let blank = self.data.text.lines().iter().all(|l| l.chars().all(|c| c.is_ascii_whitespace()));
+        if !blank {
+            self.data.todo.retain(|y| *y != x);
+        } else if !self.data.todo.contains(&x) && self.scan_path(x).is_some() {
+            let at = self.data.todo.partition_point(|y| *y < x);
+            self.data.todo.insert(at, x);
+        }
+    }
+
+    /// The scanned image of the given page, if any
+    fn scan_path(&self, x: u32) -> Option<PathBuf> {
+        let extensions = &self.data.config.image_extensions;
+        read_dir(self.data.cwd.value()).into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| {
+                extensions.iter().any(|e| ext.eq_ignore_ascii_case(e))
+            }))
+            .find(|p| p.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse::<u32>().ok()) == Some(x))
+    }
+
+    /// The pages which have a scanned image but whose text file is missing
+    /// or blank. They are only looked for when the directory is opened or
+    /// counted again, since it means reading all the pages.
+    fn untranscribed(&self) -> Result<Vec<u32>> {
+        if self.data.lazy {
+            return Ok(vec![]);
+        }
+        let extensions = &self.data.config.image_extensions;
+        let mut todo = read_dir(self.data.cwd.value())?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| {
+                extensions.iter().any(|e| ext.eq_ignore_ascii_case(e))
+            }))
+            .filter_map(|p| p.file_stem()?.to_str()?.parse::<u32>().ok())
+            .filter(|x| std::fs::read(self.fname(*x))
+                .map_or(true, |bytes| bytes.iter().all(|b| b.is_ascii_whitespace())))
+            .collect::<Vec<_>>();
+        todo.sort_unstable();
+        todo.dedup();
+        Ok(todo)
+    }
+
+    /// Searches the next occurrence of the word under the cursor
+    fn search_word(&mut self) -> Result<()> {
+        let (row, col) = self.data.text.cursor();
+        let line = self.data.text.lines()[row].chars().collect::<Vec<_>>();
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+        if !line.get(col).is_some_and(is_word) {
+            self.data.notify("No word under the cursor");
+            return Ok(());
+        }
+        let start = line[..col].iter().rposition(|c| !is_word(c)).map_or(0, |i| i + 1);
+        let end = line[col..].iter().position(|c| !is_word(c)).map_or(line.len(), |i| col + i);
+        let word = line[start..end].iter().collect::<String>();
+
+        let pattern = format!(r"\b{}\b", regex::escape(&word));
+        *self.data.srch.value_mut() = pattern;
+        self.data.srch.move_end();
+        self.data.text.set_search_pattern(self.data.search())?;
+        self.data.text.search_forward(false);
+        Ok(())
+    }
+
+    /// Only shows the lines matching the search pattern (without touching
+    /// the buffer itself)
+    fn focus(&mut self) {
+        let Some(pattern) = self.data.text.search_pattern() else {
+            self.data.notify("No search pattern");
+            return;
+        };
+        self.data.focus = self.data.text.lines().iter().enumerate()
+            .filter(|(_, line)| pattern.is_match(line))
+            .map(|(row, _)| row)
+            .collect();
+        if self.data.focus.is_empty() {
+            self.data.notify("No line matches the search pattern");
+        } else {
+            let (row, _) = self.data.text.cursor();
+            let selected = self.data.focus.iter().position(|r| *r >= row).unwrap_or_default();
+            self.data.focus_state.select(Some(selected));
+            self.set_mode(Mode::Focus);
+        }
+    }
+
+    /// Swaps the page and the scratch buffer
+    fn toggle_scratch(&mut self) -> Result<()> {
+        if self.data.in_scratch {
+            self.store_scratch()?;
+        }
+        std::mem::swap(&mut self.data.text, &mut self.data.scratch);
+        self.data.counts = None;
+        self.data.in_scratch = !self.data.in_scratch;
+        Ok(())
+    }
+
+    /// Writes the scratch buffer to the scratch file when one is configured
+    fn store_scratch(&self) -> Result<()> {
+        if let Some(fname) = self.data.config.scratch_file.as_ref() {
+            let scratch = if self.data.in_scratch { &self.data.text } else { &self.data.scratch };
+            std::fs::write(fname, scratch.lines().join("\n"))?;
+        }
+        Ok(())
+    }
+
+    fn export(&mut self) -> Result<()> {
+        let fname = self.export_fname();
+        if fname.exists() {
+            self.confirm(format!("Overwrite {} ?", fname.display()), Pending::Export);
+            Ok(())
+        } else {
+            self.write_export()
+        }
+    }
+
+    /// Exports the collection as a Markdown document (asking before an
+    /// existing one gets overwritten)
+    fn export_markdown(&mut self) -> Result<()> {
+        let fname = self.markdown_fname();
+        if fname.exists() {
+            self.confirm(format!("Overwrite {} ?", fname.display()), Pending::Markdown);
+            Ok(())
+        } else {
+            self.write_markdown()
+        }
+    }
+
+    fn markdown_fname(&self) -> PathBuf {
+        let cwd = PathBuf::from_str(self.data.cwd.value()).unwrap();
+        cwd.join(&self.data.config.export.markdown)
+    }
+
+    fn write_markdown(&mut self) -> Result<()> {
+        let pages = self.page_ids()?.into_iter()
+            .map(|x| (x, self.fname(x)))
+            .filter(|(_, path)| path.is_file())
+            .collect::<Vec<_>>();
+        let text = markdown(&pages, &self.data.config.export)?;
+
+        let fname = self.markdown_fname();
+        std::fs::write(&fname, text)?;
+        self.data.notify(format!("Exported {} pages to {}", pages.len(), fname.display()));
+        Ok(())
+    }
+
+    fn export_fname(&self) -> PathBuf {
+        let cwd = PathBuf::from_str(self.data.cwd.value()).unwrap();
+        cwd.join(&self.data.config.export.output)
+    }
+
+    fn write_export(&mut self) -> Result<()> {
+        let pages = self.page_ids()?.into_iter()
+            .map(|x| (x, self.fname(x)))
+            .filter(|(_, path)| path.is_file())
+            .collect::<Vec<_>>();
+        let text = concatenate(&pages, &self.data.config.export)?;
+
+        let fname = self.export_fname();
+        std::fs::write(&fname, text)?;
+        self.data.notify(format!("Exported {} pages to {}", pages.len(), fname.display()));
+        Ok(())
+    }
+
+    /// Turns every match of the search pattern into explicit markup
+    fn apply_markup(&mut self) {
+        let Some(pattern) = self.data.text.search_pattern().cloned() else {
+            self.data.notify("No search pattern");
+            return;
+        };
+        let markup = self.data.config.markup.as_str();
+        let mut count = 0;
+        let lines = self.data.text.lines().iter()
+            .map(|line| {
+                count += pattern.find_iter(line).count();
+                pattern.replace_all(line, markup).to_string()
+            })
+            .collect();
+        if count > 0 {
+            let cursor = self.data.text.cursor();
+            self.rewrite(lines, cursor);
+        }
+        self.data.notify(format!("{count} matches turned into markup"));
+    }
+
+    /// Capitalizes the first letter of every sentence in the buffer
+    fn capitalize(&mut self) {
+        let (lines, count) = capitalize(self.data.text.lines(), &self.data.config.abbreviations);
+        if count > 0 {
+            let cursor = self.data.text.cursor();
+            self.rewrite(lines, cursor);
+        }
+        self.data.notify(format!("{count} sentence starts capitalized"));
+    }
+
+    /// Flags the current file for review (asking for a note), or removes its
+    /// flag when it already has one
+    fn toggle_flag(&mut self) -> Result<()> {
+        if self.data.path.is_some() {
+            self.data.notify("Only the pages can be flagged");
+            return Ok(());
+        }
+        let x: u32 = self.data.curr.value().parse()?;
+        if self.data.flags.remove(&x).is_some() {
+            self.data.notify(format!("Unflagged {}", self.id(x)));
+        } else {
+            self.data.note = TextState::new();
+            self.set_mode(Mode::Flag);
+        }
+        Ok(())
+    }
+
+    /// Writes the list of the flagged files and their notes to the report
+    fn export_flags(&mut self) -> Result<()> {
+        let report = self.data.flags.iter()
+            .map(|(x, note)| format!("{}: {note}\n", self.fname(*x).display()))
+            .collect::<String>();
+        let cwd = PathBuf::from_str(self.data.cwd.value()).unwrap();
+        let fname = cwd.join(&self.data.config.export.flags);
+        std::fs::write(&fname, report)?;
+        self.data.notify(format!("{} flagged files listed in {}", self.data.flags.len(), fname.display()));
+        Ok(())
+    }
+
+    /// Asks for a confirmation before splitting the buffer at its headings
+    fn ask_split_at_headings(&mut self) -> Result<()> {
+        if self.data.in_scratch {
+            self.data.notify("The scratch buffer is never saved to the pages");
+            return Ok(());
+        }
+        if self.data.path.is_some() {
+            self.data.notify("Only the pages can be split at their headings");
+            return Ok(());
+        }
+        let count = sections(self.data.text.lines()).len();
+        if count < 2 {
+            self.data.notify("No heading to split at");
+            return Ok(());
+        }
+        let x: u32 = self.data.curr.value().parse()?;
+        let last = x + count as u32 - 1;
+        let existing = (x + 1..=last).filter(|y| self.fname(*y).exists()).count();
+        self.confirm(
+            format!("Split into files {} to {} ({existing} existing files overwritten) ?", self.id(x), self.id(last)),
+            Pending::Split);
+        Ok(())
+    }
+
+    /// Writes each section of the buffer to its own file, starting with the
+    /// current one
+    fn split_at_headings(&mut self) -> Result<()> {
+        if self.data.in_scratch || self.data.path.is_some() {
+            return Ok(());
+        }
+        let x: u32 = self.data.curr.value().parse()?;
+        let sections = sections(self.data.text.lines());
+        for (i, section) in sections.iter().enumerate() {
+            files::write(&self.fname(x + i as u32), section.join(self.data.eol).as_bytes())?;
+        }
+        // the sections are never blank
+        let written = x..x + sections.len() as u32;
+        self.data.todo.retain(|y| !written.contains(y));
+        // the whole buffer is saved in the sections
+        self.data.dirty = false;
+        self.data.tot = self.count()?;
+        self.load(x)?;
+        self.data.notify(format!("Created {} files", sections.len()));
+        Ok(())
+    }
+
+    /// Reports the number of lines exceeding the maximum line length
+    fn check_line_length(&mut self) {
+        if let Some(max) = self.data.config.max_line_length {
+            let count = self.data.text.lines().iter()
+                .filter(|l| l.chars().count() > max)
+                .count();
+            if count > 0 {
+                self.data.notify(format!("{count} lines longer than {max} characters"));
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<()> {
+        if self.data.path.is_some() {
+            self.data.notify("The file is not a page, open one (f) to move through the pages again");
+            return Ok(());
+        }
+        if self.data.config.autosave.is_some() {
+            self.autosave()?;
+        }
+        let curr: u32 = self.data.curr.value().parse()?;
+        match self.following(curr)? {
+            Some(x) => self.step(x),
+            None    => {
+                self.data.notify("Already at last file");
+                self.cue(self.data.config.boundary_cue);
+                Ok(())
+            },
+        }
+    }
+    
+    fn prev(&mut self) -> Result<()> {
+        if self.data.path.is_some() {
+            self.data.notify("The file is not a page, open one (f) to move through the pages again");
+            return Ok(());
+        }
+        if self.data.config.autosave.is_some() {
+            self.autosave()?;
+        }
+        let curr: u32 = self.data.curr.value().parse()?;
+        match self.preceding(curr)? {
+            Some(x) => self.step(x),
+            None    => {
+                self.data.notify("Already at first file");
+                self.cue(self.data.config.boundary_cue);
+                Ok(())
+            },
+        }
+    }
+    /// Loads the page that next or prev moved to. The stacked split slides
+    /// along, so that it keeps showing two pages which follow each other:
+    /// the page that is left stays in the other pane.
+    fn step(&mut self, x: u32) -> Result<()> {
+        let Some(pane) = self.data.pane.as_ref().filter(|pane| pane.stacked && pane.x != x) else {
+            return self.load(x);
+        };
+        if pane.dirty {
+            self.data.notify(format!("{} has unsaved changes, switch to it (ctrl+w) and save it first", self.id(pane.x)));
+            return Ok(());
+        }
+        self.switch_pane();
+        self.load(x)?;
+        if let Some(pane) = self.data.pane.as_mut() {
+            pane.first = pane.x < x;
+        }
+        Ok(())
+    }
+    /// The page which comes after the given one, if any. In lazy mode, only
+    /// the very next id is looked for (the directory is not listed).
+    fn following(&self, x: u32) -> Result<Option<u32>> {
+        if self.data.lazy {
+            return Ok(x.checked_add(1).filter(|y| self.fname(*y).is_file()));
+        }
+        Ok(self.page_ids()?.into_iter().find(|y| *y > x))
+    }
+    /// The page which comes before the given one, if any. In lazy mode, only
+    /// the very previous id is looked for (the directory is not listed).
+    fn preceding(&self, x: u32) -> Result<Option<u32>> {
+        if self.data.lazy {
+            return Ok(x.checked_sub(1).filter(|y| self.fname(*y).is_file()));
+        }
+        Ok(self.page_ids()?.into_iter().rev().find(|y| *y < x))
+    }
+
+    /// Draws the attention of the user with the given cue
+    fn cue(&mut self, cue: Cue) {
+        match cue {
+            Cue::None  => { /* the message is enough */ },
+            Cue::Flash => { self.data.flash = Some(Instant::now() + FLASH); },
+            Cue::Bell  => {
+                let mut out = std::io::stdout();
+                let rung = std::io::Write::write_all(&mut out, b"\x07")
+                    .and_then(|_| std::io::Write::flush(&mut out));
+                if let Err(e) = rung {
+                    self.data.notify(format!("{e}"));
+                }
+            },
+        }
+    }
+
+    /// Replaces the whole content of the buffer in a way that can be undone,
+    /// then moves the cursor to the given position
+    fn rewrite(&mut self, lines: Vec<String>, (row, col): (usize, usize)) {
+        self.data.text.select_all();
+        self.data.text.insert_str(lines.join("\n"));
+        self.modified();
+        self.data.text.move_cursor(tui_textarea::CursorMove::Jump(row.min(u16::MAX as usize) as u16, col.min(u16::MAX as usize) as u16));
+    }
+
+    /// Records that the page buffer has changes which are not saved yet (the
+    /// scratch buffer is stored on exit anyway)
+    fn modified(&mut self) {
+        self.data.counts = None;
+        if !self.data.in_scratch {
+            self.data.dirty = true;
+        }
+    }
+
+    /// Inserts the content of the clipboard at the cursor
+    fn paste(&mut self) {
+        match self.data.clipboard.get() {
+            Some(text) => {
+                let text = text.replace("\r\n", "\n");
+                let count = text.lines().count();
+                self.data.text.insert_str(text);
+                self.modified();
+                self.data.notify(format!("{count} lines pasted"));
+            },
+            None => { self.data.notify("The clipboard is empty, holds no text or cannot be read"); },
+        }
+    }
+
+    /// Copies the selection to the clipboard as a quoted block, each line
+    /// being prefixed with the quote marker
+    fn copy_quoted(&mut self) -> Result<()> {
+        self.data.text.copy();
+        let text = self.data.text.yank_text();
+        let quote = &self.data.config.quote;
+        let block = text.lines()
+            .map(|line| format!("{quote}{line}"))
+            .collect::<Vec<_>>();
+        let count = block.len();
+        self.data.clipboard.set(block.join("\n"))?;
+        self.data.notify(format!("{count} lines copied as a quote"));
+        Ok(())
+    }
+
+    /// Lists the registers (along with the start of their content) in the
+    /// message log
+    fn list_registers(&mut self) {
+        let registers = self.data.registers.iter()
+            .map(|(name, text)| {
+                let first = text.lines().next().unwrap_or_default();
+                format!("{name}: {} lines, {first}", text.lines().count())
+            })
+            .collect::<Vec<_>>();
+        let count = registers.len();
+        for register in registers {
+            self.data.notify(register);
+        }
+        self.data.notify(format!("{count} registers"));
+        self.set_mode(Mode::Log);
+        self.data.log_scroll = 0;
+    }
+
+    /// Inserts the page-break marker on its own line before the current one
+    fn insert_page_break(&mut self) {
+        let marker = format!("{}\n", self.data.config.export.page_break);
+        self.data.text.move_cursor(tui_textarea::CursorMove::Head);
+        self.data.text.insert_str(marker);
+        self.modified();
+    }
+
+    fn insert_footnote(&mut self) {
+        let (lines, cursor) = footnotes::insert(self.data.text.lines(), self.data.text.cursor());
+        self.rewrite(lines, cursor);
+    }
+
+    /// Joins the given rows into a single line (the inverse of the long
+    /// lines splitting), each break becoming a single space
+    fn join_lines(&mut self, rows: std::ops::Range<usize>) {
+        let lines = self.data.text.lines();
+        let rows = rows.start..rows.end.min(lines.len());
+        if rows.len() < 2 {
+            self.data.notify("No line to join");
+            return;
+        }
+        let first = lines[rows.start].trim_end();
+        let col = first.chars().count();
+        let joined = lines[rows.clone()].iter()
+            .enumerate()
+            .map(|(i, line)| if i == 0 { line.trim_end() } else { line.trim() })
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = [&lines[..rows.start], &[joined], &lines[rows.end..]].concat();
+        self.rewrite(text, (rows.start, col));
+    }
+
+    /// Changes the case of the selection in place (the yank buffer is left
+    /// untouched), then goes back to the command mode
+    fn change_case(&mut self, case: Case) {
+        let yank = self.data.text.yank_text();
+        self.set_mode(Mode::Command);
+        if !self.data.text.cut() {
+            self.data.notify("Nothing is selected");
+            return;
+        }
+        let selection = self.data.text.yank_text();
+        self.data.text.insert_str(case.apply(&selection));
+        self.data.text.set_yank_text(yank);
+        self.modified();
+    }
+
+    /// Removes the trailing whitespace of the given rows. The cursor stays
+    /// where it was, or at the end of its line when that got shorter.
+    fn trim_trailing(&mut self, rows: std::ops::Range<usize>) {
+        let mut lines = self.data.text.lines().to_vec();
+        let rows = rows.start.min(lines.len())..rows.end.min(lines.len());
+        let mut count = 0;
+        for line in lines[rows].iter_mut() {
+            let trimmed = line.trim_end();
+            if trimmed.len() < line.len() {
+                *line = trimmed.to_string();
+                count += 1;
+            }
+        }
+        if count == 0 {
+            self.data.notify("No trailing whitespace");
+            return;
+        }
+        let cursor = self.data.text.cursor();
+        self.rewrite(lines, cursor);
+        self.data.notify(format!("Trailing whitespace removed from {count} lines"));
+    }
+
+    fn jump_footnote(&mut self) {
+        match footnotes::counterpart(self.data.text.lines(), self.data.text.cursor()) {
+            Some((row, col)) => self.data.text.move_cursor(tui_textarea::CursorMove::Jump(row.min(u16::MAX as usize) as u16, col.min(u16::MAX as usize) as u16)),
+            None => self.data.notify("No footnote under the cursor"),
+        }
+    }
+
+    fn split_long_lines(&mut self) -> Result<()> {
+        let runs = split_runs(&self.data.config.split, self.data.min_run)?;
+        let (text, skipped) = split_lines(&self.data.config.split, &runs, self.data.text.lines())?;
+        let cursor = self.data.text.cursor();
+        self.rewrite(text, cursor);
+        self.report_skipped(skipped);
+        Ok(())
+    }
+
+    /// Collapses each run of blank lines into a single empty line
+    fn squeeze_blank_lines(&mut self) {
+        let lines = self.data.text.lines();
+        let squeezed = squeeze_blank_lines(lines);
+        let removed = lines.len() - squeezed.len();
+        if removed == 0 {
+            self.data.notify("No consecutive blank lines");
+            return;
+        }
+        // the cursor stays on its line, or on what is left of its run
+        let (row, col) = self.data.text.cursor();
+        let row = squeeze_blank_lines(&lines[..=row]).len() - 1;
+        self.rewrite(squeezed, (row, col));
+        self.data.notify(format!("{removed} blank lines removed"));
+    }
+
+    /// Splits the long lines of the paragraph under the cursor only
+    fn split_paragraph(&mut self) -> Result<()> {
+        let paragraph = self.paragraph();
+        if paragraph.is_empty() {
+            self.data.notify("No paragraph under the cursor");
+            return Ok(());
+        }
+        let start = paragraph.start;
+        let lines = self.data.text.lines();
+        let runs = split_runs(&self.data.config.split, self.data.min_run)?;
+        let (split, skipped) = split_lines(&self.data.config.split, &runs, &lines[paragraph.clone()])?;
+        let text = [&lines[..start], &split, &lines[paragraph.end..]].concat();
+        self.rewrite(text, (start, 0));
+        self.report_skipped(skipped);
+        Ok(())
+    }
+
+    /// Rewraps the words of the paragraph under the cursor so that its lines
+    /// are at most as wide as the reflow width. The blank lines around it
+    /// are left untouched.
+    fn reflow_paragraph(&mut self) {
+        let paragraph = self.paragraph();
+        if paragraph.is_empty() {
+            self.data.notify("No paragraph under the cursor");
+            return;
+        }
+        let start = paragraph.start;
+        let lines = self.data.text.lines();
+        let reflowed = reflow(&lines[paragraph.clone()], self.data.reflow_width);
+        let count = reflowed.len();
+        let text = [&lines[..start], &reflowed, &lines[paragraph.end..]].concat();
+        self.rewrite(text, (start, 0));
+        self.data.notify(format!("Paragraph reflowed to {count} lines of at most {} characters", self.data.reflow_width));
+    }
+
+    /// The rows of the paragraph (the lines between two blank lines) under
+    /// the cursor. There is none (the range is empty) when the cursor is on
+    /// a blank line.
+    fn paragraph(&self) -> std::ops::Range<usize> {
+        let lines = self.data.text.lines();
+        let (row, _) = self.data.text.cursor();
+        let blank = |l: &String| l.trim().is_empty();
+        if lines.get(row).is_none_or(blank) {
+            return row..row;
+        }
+        let start = lines[..row].iter().rposition(blank).map_or(0, |r| r + 1);
+        let end = lines[row..].iter().position(blank).map_or(lines.len(), |r| row + r);
+        start..end.max(start)
+    }
+
+    /// Previews the paragraph under the cursor as a delimited table and asks
+    /// for a confirmation before replacing it
+    fn ask_columns(&mut self) -> Result<()> {
+        let rows = self.paragraph();
+        let runs = split_runs(&self.data.config.split, self.data.min_run)?;
+        let table = columns(&self.data.text.lines()[rows.clone()], &runs, &self.data.config.delimiter);
+        if table.is_empty() {
+            self.data.notify("No table under the cursor");
+            return Ok(());
+        }
+        self.confirm(format!("Delimit the columns of {} rows ?", table.len()), Pending::Columns(rows, table));
+        Ok(())
+    }
+
+    fn report_skipped(&mut self, skipped: usize) {
+        if skipped > 0 {
+            self.data.notify(format!("{skipped} lines too long to be split"));
+        }
+    }
+}
+
+/// The case a selection can be changed to
+#[derive(Debug, Clone, Copy)]
+enum Case {
+    Upper,
+    Lower,
+    /// The first letter of each (whitespace delimited) word in upper case,
+    /// the others in lower case
+    Title,
+}
+
+impl Case {
+    fn apply(self, text: &str) -> String {
+        match self {
+            Case::Upper => text.to_uppercase(),
+            Case::Lower => text.to_lowercase(),
+            Case::Title => {
+                let mut out = String::with_capacity(text.len());
+                let mut start = true;
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        start = true;
+                        out.push(c);
+                    } else if start && c.is_alphabetic() {
+                        start = false;
+                        out.extend(c.to_uppercase());
+                    } else {
+                        out.extend(c.to_lowercase());
+                    }
+                }
+                out
+            },
+        }
+    }
+}
+
+/// Joins the given lines, then splits them again greedily so that no line is
+/// wider than `width` characters (except for the words that are wider on
+/// their own). The indentation of the first line is kept.
+fn reflow(lines: &[String], width: usize) -> Vec<String> {
+    let first = lines.first().map_or("", |l| l.as_str());
+    let mut line = first[..first.len() - first.trim_start().len()].to_string();
+    let mut reflowed = vec![];
+    for word in lines.iter().flat_map(|l| l.split_whitespace()) {
+        if !line.trim_start().is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            reflowed.push(std::mem::take(&mut line));
+        }
+        if !line.trim_start().is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    reflowed.push(line);
+    reflowed
+}
+
+/// Collapses each run of two or more blank lines (whitespace only lines
+/// included) into a single empty line. The runs at the start and at the end
+/// of the lines are no exception: they are collapsed too, not removed.
+fn squeeze_blank_lines(lines: &[String]) -> Vec<String> {
+    // the terminating line break makes the last line end like all the others
+    let text = lines.join("\n") + "\n";
+    let text = BLANK_RUNS.replace_all(&text, "\n");
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    text.split('\n').map(|l| l.to_string()).collect()
+}
+
+/// Splits the given lines on the long runs of whitespace. Along with the
+/// split lines, this returns the number of lines that were too long to be
+/// split.
+fn split_lines(split: &SplitConfig, runs: &Regex, lines: &[String]) -> Result<(Vec<String>, usize)> {
+    let keep = split.keep.as_deref().map(Regex::new).transpose()?;
+    let mut skipped = 0;
+    let mut text = vec![];
+    for line in lines {
+        if keep.as_ref().is_some_and(|keep| keep.is_match(line)) {
+            text.push(line.clone());
+        } else if line.len() > split.guard {
+            skipped += 1;
+            text.push(line.clone());
+        } else {
+            text.extend(runs.split(line).map(|s| s.to_owned()));
+        }
+    }
+    Ok((text, skipped))
+}
+
+/// Turns the lines of a visually aligned table into delimited ones: the runs
+/// of whitespace separating the columns are replaced with the delimiter
+fn columns(lines: &[String], runs: &Regex, delimiter: &str) -> Vec<String> {
+    lines.iter()
+        .map(|line| runs.replace_all(line.trim_end(), delimiter).into_owned())
+        .collect()
+}
+
+/// Capitalizes the lowercase letters starting a sentence, unless the
+/// previous sentence seemingly ended with one of the abbreviations. Along
+/// with the new lines, this returns the number of letters that changed.
+fn capitalize(lines: &[String], abbreviations: &[String]) -> (Vec<String>, usize) {
+    let text = lines.join("\n");
+    let mut count = 0;
+    let text = SENTENCES.replace_all(&text, |caps: &regex::Captures| {
+        let end = caps.get(0).unwrap().start();
+        let word = text[..end].rsplit(char::is_whitespace).next().unwrap_or_default();
+        let word = format!("{word}{}", &caps[1]);
+        let word = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+        if abbreviations.iter().any(|a| word == a) {
+            caps[0].to_string()
+        } else {
+            count += 1;
+            format!("{}{}{}", &caps[1], &caps[2], caps[3].to_uppercase())
+        }
+    });
+    (text.split('\n').map(|l| l.to_string()).collect(), count)
+}
+
+/// The lines of the content of a file, where the invalid bytes are shown as
+/// U+FFFD so that they can be fixed. Along with the lines, this returns the
+/// line ending to use when they get written back (which is always \n unless
+/// the line breaks are normalized).
+fn decode(bytes: &[u8], normalize: bool) -> (Vec<String>, &'static str) {
+    let text = String::from_utf8_lossy(bytes);
+    if normalize {
+        let (text, eol) = normalize_newlines(&text);
+        (text.lines().map(|s| s.to_owned()).collect(), eol)
+    } else {
+        (text.lines().map(|s| s.to_owned()).collect(), "\n")
+    }
+}
+
+/// Converts all the recognized line breaks to \n. Along with the normalized
+/// text, this returns the line ending to use when the text gets written back
+/// which is \r\n only when the file consistently used it.
+fn normalize_newlines(text: &str) -> (String, &'static str) {
+    let crlf = text.matches("\r\n").count();
+    let eol = if crlf > 0 && crlf == text.matches('\n').count() { "\r\n" } else { "\n" };
+    let text = text
+        .replace("\r\n", "\n")
+        .replace(['\r', '\u{2028}', '\u{2029}', '\u{85}'], "\n");
+    (text, eol)
+}
+
+/// Splits the lines in sections, each of which starts at a `###` heading.
+/// Bare `###` lines are mere separators and are dropped, whereas the titled
+/// headings are kept at the top of their section.
+fn sections(lines: &[String]) -> Vec<Vec<String>> {
+    let mut sections = vec![vec![]];
+    for line in lines {
+        if line.starts_with("###") {
+            sections.push(vec![]);
+            if line.trim() == "###" {
+                continue;
+            }
+        }
+        sections.last_mut().unwrap().push(line.clone());
+    }
+    sections.retain(|s: &Vec<String>| s.iter().any(|l| !l.trim().is_empty()));
+    sections
+}
+
+impl Data<'_> {
+    fn new(config: Config, cwd: &Path) -> Self {
+        let default_search = config.highlight.clone();
+        let scratch = config.scratch_file.as_ref()
+            .and_then(|fname| std::fs::read_to_string(fname).ok())
+            .map(|text| text.lines().map(|s| s.to_owned()).collect())
+            .unwrap_or_default();
+        Self { 
+            mode: Mode::Command,
+            text: textarea(vec![], &default_search),
+            cwd : TextState::new().with_value(cwd.to_string_lossy().to_string()),
+            curr: TextState::new().with_value("000"),
+            tot : 1,
+            srch: TextState::new().with_value(default_search.clone()),
+            highlight: config.highlight.clone(),
+            search_ci: false,
+            search_word: false,
+            msg : String::new(),
+            scratch: textarea(scratch, &default_search),
+            in_scratch: false,
+            confirm: None,
+            log : VecDeque::new(),
+            log_scroll: 0,
+            keys_scroll: 0,
+            chars: ListState::default(),
+            keymap: keys::KeyMap::default(),
+            palette: TextState::new(),
+            palette_state: ListState::default(),
+            focus: vec![],
+            focus_state: ListState::default(),
+            recent: vec![],
+            recent_state: ListState::default(),
+            opened: cwd.to_string_lossy().to_string(),
+            lazy: false,
+            flash: None,
+            counts: None,
+            file: TextState::new(),
+            new_page: TextState::new(),
+            path: None,
+            pane: None,
+            pane_id: TextState::new(),
+            next_reload: config.reload_interval
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            next_autosave: config.autosave
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            cmp : TextState::new(),
+            compare: None,
+            save_as: TextState::new(),
+            eol : "\n",
+            last_msg: String::new(),
+            count: None,
+            flags: BTreeMap::new(),
+            note: TextState::new(),
+            clipboard: config.clipboard.open(),
+            hex : vec![],
+            hex_scroll: 0,
+            changes: vec![],
+            changes_scroll: 0,
+            view: config.view,
+            gauge: config.gauge,
+            global: TextState::new(),
+            goto: TextState::new(),
+            overwrite: false,
+            anchor: (0, 0),
+            search_origin: (0, 0),
+            searches: if config.persist_search_history {
+                Recent::load().map(|recent| recent.searches).unwrap_or_default()
+            } else {
+                vec![]
+            },
+            recalled: None,
+            draft: String::new(),
+            line_counts: HashMap::new(),
+            min_run: config.split.min_run,
+            reflow_width: config.reflow_width,
+            show_scan: false,
+            scan: None,
+            replacement: TextState::new(),
+            pages_pattern: TextState::new(),
+            registers: BTreeMap::new(),
+            register: TextState::new(),
+            stash: String::new(),
+            pad: config.pad.unwrap_or(3),
+            pad_width: TextState::new(),
+            todo: vec![],
+            received: None,
+            dirty: false,
+            zen : config.distraction_free,
+            peek: false,
+            latency: None,
+            config,
+        }
+    }
+    /// The name of the mode as it is shown, which tells the overwrite typing
+    /// apart from the insert one
+    fn mode_name(&self) -> String {
+        match self.mode {
+            Mode::Input if self.overwrite => "OVERWRITE".to_string(),
+            mode => mode.to_string(),
+        }
+    }
+    /// True iff a file (of either pane) has unsaved changes
+    fn unsaved(&self) -> bool {
+        self.dirty || self.pane.as_ref().is_some_and(|pane| pane.dirty)
+    }
+    /// The search pattern along with its active modifiers
+    fn search(&self) -> String {
+        let pattern = self.srch.value();
+        if pattern.is_empty() {
+            return String::new();
+        }
+        let pattern = if self.search_word { format!(r"\b(?:{pattern})\b") } else { pattern.to_string() };
+        if self.search_ci { format!("(?i){pattern}") } else { pattern }
+    }
+    /// The commands of the command mode whose key or description contains the
+    /// filter of the palette (ignoring the case), as their default key, the
+    /// key they are bound to and their description
+    fn commands(&self) -> Vec<(&'static str, String, &'static str)> {
+        let filter = self.palette.value().to_lowercase();
+        keys::command_keys()
+            .map(|(action, key, what)| (*key, self.keymap.key(action, key), *what))
+            .filter(|(_, key, what)| key.to_lowercase().contains(&filter) || what.to_lowercase().contains(&filter))
+            .collect()
+    }
+    /// Displays a status message and keeps a trace of it in the log
+    fn notify(&mut self, msg: impl Into<String>) {
+        self.msg = msg.into();
+        while !self.log.is_empty() && self.log.len() >= self.config.log_size {
+            self.log.pop_front();
+        }
+        if self.config.log_size > 0 {
+            self.log.push_back(self.msg.clone());
+        }
+    }
+}
+
+/// The number of graphemes made of the characters `cols` of the line
+fn graphemes(line: &str, cols: std::ops::Range<usize>) -> usize {
+    let start = line.char_indices().nth(cols.start).map_or(line.len(), |(i, _)| i);
+    let end   = line.char_indices().nth(cols.end).map_or(line.len(), |(i, _)| i);
+    line.get(start..end.max(start)).map_or(0, |text| text.graphemes(true).count())
+}
+
+/// The number of graphemes (each line break counting as one) between two
+/// positions of the text, in any order
+fn selected(lines: &[String], a: (usize, usize), b: (usize, usize)) -> usize {
+    let (from, to) = if a <= b { (a, b) } else { (b, a) };
+    let line = |row: usize| lines.get(row).map_or("", |l| l.as_str());
+    if from.0 == to.0 {
+        return graphemes(line(from.0), from.1..to.1);
+    }
+    let first = graphemes(line(from.0), from.1..usize::MAX);
+    let inner = (from.0 + 1..to.0).map(|row| line(row).graphemes(true).count()).sum::<usize>();
+    let last  = graphemes(line(to.0), 0..to.1);
+    first + inner + last + (to.0 - from.0)
+}
+
+/// The rank (from 1) of the match of the pattern the cursor is on, if any,
+/// and the number of matches in the lines. Just like the search itself, the
+/// matches never span several lines.
+fn match_counter(lines: &[String], pattern: &Regex, cursor: (usize, usize)) -> (Option<usize>, usize) {
+    let mut current = None;
+    let mut tot = 0;
+    for (row, line) in lines.iter().enumerate() {
+        for m in pattern.find_iter(line) {
+            tot += 1;
+            if (row, line[..m.start()].chars().count()) == cursor {
+                current = Some(tot);
+            }
+        }
+    }
+    (current, tot)
+}
+
+/// The number of words (runs of non-whitespace characters) and characters
+/// (graphemes, the line breaks excluded) of the lines
+fn counts(lines: &[String]) -> (usize, usize) {
+    lines.iter().fold((0, 0), |(words, chars), line| {
+        (words + line.split_whitespace().count(), chars + line.graphemes(true).count())
+    })
+}
+
+/// A rectangle of the given size (in percents) centered in the area
+fn popup(area: Rect, width: u16, height: u16) -> Rect {
+    let vertical = Layout::vertical([
+        Constraint::Percentage((100 - height) / 2),
+        Constraint::Percentage(height),
+        Constraint::Percentage((100 - height) / 2),
+    ]).split(area);
+    Layout::horizontal([
+        Constraint::Percentage((100 - width) / 2),
+        Constraint::Percentage(width),
+        Constraint::Percentage((100 - width) / 2),
+    ]).split(vertical[1])[1]
+}
+
+/// A classic hex dump of the bytes (16 per row) where the control bytes and
+/// the non-ascii ones are highlighted
+fn hexdump(bytes: &[u8]) -> Vec<ratatui::text::Line<'static>> {
+    use ratatui::text::{Line, Span};
+    let plain = Style::default().fg(Color::LightCyan);
+    let odd   = Style::default().fg(Color::Red);
+    bytes.chunks(16).enumerate()
+        .map(|(row, chunk)| {
+            let mut spans = vec![Span::styled(format!("{:08x}  ", row * 16), Style::default().fg(Color::DarkGray))];
+            for b in chunk {
+                let style = if (b.is_ascii_control() && *b != b'\n') || !b.is_ascii() { odd } else { plain };
+                spans.push(Span::styled(format!("{b:02x} "), style));
+            }
+            spans.push(Span::raw("   ".repeat(16 - chunk.len())));
+            let ascii = chunk.iter()
+                .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' })
+                .collect::<String>();
+            spans.push(Span::styled(format!(" |{ascii}|"), plain));
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// One of the files of a comparison, where the lines which differ from the
+/// other file are highlighted
+fn side<'a>((x, lines): &'a (u32, Vec<String>), other: &[String], scroll: u16, pad: usize) -> Paragraph<'a> {
+    let lines = lines.iter().enumerate()
+        .map(|(i, line)| {
+            let style = if other.get(i) == Some(line) {
+                Style::default().fg(Color::LightCyan)
+            } else {
+                Style::default().fg(Color::Yellow)
+            };
+            ratatui::text::Line::styled(line.as_str(), style)
+        })
+        .collect::<Vec<_>>();
+    Paragraph::new(lines)
+        .block(Block::new().borders(Borders::all()).title(format!("{x:0pad$}")))
+        .scroll((scroll, 0))
+}
+
+impl View {
+    fn new() -> Self {
+        Self { viewport: Viewport::default() }
+    }
+
+    /// Flags the parts of the text that deserve attention
+    fn decorate(&self, data: &Data, buf: &mut Buffer) {
+        let lines = data.text.lines();
+        if data.view.whitespace {
+            let marker = Style::default().fg(Color::DarkGray);
+            for row in self.viewport.rows().filter(|r| *r < lines.len()) {
+                for (col, c) in lines[row].chars().enumerate() {
+                    match c {
+                        ' '  => self.viewport.replace(buf, row, &lines[row], col, "·", marker),
+                        '\t' => self.viewport.replace(buf, row, &lines[row], col, "→", marker),
+                        _ => { /* visible already */ }
+                    }
+                }
+            }
+        }
+        if data.view.trailing {
+            let trailing = Style::default().bg(data.config.theme.trailing());
+            for row in self.viewport.rows().filter(|r| *r < lines.len()) {
+                let line = &lines[row];
+                let len = line.chars().count();
+                let end = line.trim_end().chars().count();
+                if end < len {
+                    self.viewport.restyle(buf, row, line, end..len, trailing);
+                }
+            }
+        }
+        if matches!(data.mode, Mode::Cuts) {
+            let cut = Style::default().bg(Color::Magenta);
+            if let Ok(runs) = split_runs(&data.config.split, data.min_run) {
+                for row in self.viewport.rows().filter(|r| *r < lines.len()) {
+                    let line = &lines[row];
+                    for m in runs.find_iter(line) {
+                        let cols = line[..m.start()].chars().count()..line[..m.end()].chars().count();
+                        self.viewport.restyle(buf, row, line, cols, cut);
+                    }
+                }
+            }
+        }
+        if let Some(max) = data.config.max_line_length {
+            let flag = Style::default().bg(Color::Red).fg(Color::White);
+            let over = Style::default().fg(Color::Red).add_modifier(Modifier::UNDERLINED);
+            for row in self.viewport.rows().filter(|r| *r < lines.len()) {
+                let len = lines[row].chars().count();
+                if len > max {
+                    self.viewport.restyle_gutter(buf, row, flag);
+                    self.viewport.restyle(buf, row, &lines[row], max..len, over);
+                }
+            }
+        }
+    }
+
+    fn ui(&mut self, data: &mut Data, frame: &mut Frame) {
+        let size = frame.size();
+        let (width, height) = data.config.min_size;
+        if size.width < width || size.height < height {
+            let msg = Paragraph::new(format!(
+                    "Terminal too small ({}x{}), at least {width}x{height} is needed",
+                    size.width, size.height))
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: true });
+            frame.render_widget(msg, size);
+            return;
+        }
+
+        // without distraction, the chrome only shows when it is asked for or
+        // needed (messages, prompts)
+        let chrome = !data.zen || data.peek;
+        let status = chrome || !data.msg.is_empty()
+            || !matches!(data.mode, Mode::Command | Mode::Input | Mode::Selection | Mode::History);
+        let layout = Layout::vertical([
+            Constraint::Length(u16::from(chrome)),
+            Constraint::Min(0),
+            Constraint::Length(u16::from(status)),
+        ]).split(frame.size());
+
+        let title = if data.in_scratch {
+            Block::new()
+                .title_alignment(ratatui::layout::Alignment::Center)
+                .title("SCRATCH (not saved to the pages)")
+                .add_modifier(Modifier::BOLD)
+                .set_style(Style::default().bg(Color::Yellow).fg(Color::Black))
+        } else {
+            let flag = data.curr.value().parse().ok()
+                .filter(|_| data.path.is_none())
+                .and_then(|x: u32| data.flags.get(&x))
+                .map(|note| format!(" [flagged: {note}]"))
+                .unwrap_or_default();
+            let todo = match data.curr.value().parse() {
+                _ if data.path.is_some()        => String::new(),
+                Ok(x) if data.todo.contains(&x) => format!(" [no text yet, {} pages to transcribe]", data.todo.len()),
+                _ if !data.todo.is_empty()      => format!(" [{} pages to transcribe]", data.todo.len()),
+                _ => String::new(),
+            };
+            let name = data.path.as_ref()
+                .map_or_else(|| data.curr.value().to_string(), |path| path.display().to_string());
+            Block::new()
+                .title_alignment(ratatui::layout::Alignment::Center)
+                .title(format!("{name}{flag}{todo}"))
+                .add_modifier(Modifier::BOLD)
+                .set_style(Style::default().bg(Color::White).fg(Color::Blue))
+        };
+
+        let name = data.mode_name();
+        let mode = Block::new()
+            .title_alignment(ratatui::layout::Alignment::Right)
+            .style(Style::default().bg(data.config.theme.mode(&name)).fg(Color::Black))
+            .title(name);
+
+        let title = match data.latency.as_ref().filter(|l| !l.is_empty()) {
+            Some(latency) => {
+                let average = latency.iter().sum::<Duration>() / latency.len() as u32;
+                let average = format!("{:.1} ms ", average.as_secs_f64() * 1000.0);
+                title.title(Title::from(average).alignment(ratatui::layout::Alignment::Right))
+            },
+            None => title,
+        };
+        frame.render_widget(title, layout[0]);
+        if let (Mode::SideBySide, Some(compare)) = (data.mode, data.compare.as_ref()) {
+            let panes = Layout::horizontal([
+                Constraint::Percentage(50),
+                Constraint::Percentage(50),
+            ]).split(layout[1]);
+            frame.render_widget(side(&compare.left, &compare.right.1, compare.scroll, data.pad), panes[0]);
+            frame.render_widget(side(&compare.right, &compare.left.1, compare.scroll, data.pad), panes[1]);
+        } else if matches!(data.mode, Mode::Hex) {
+            let hex = Paragraph::new(hexdump(&data.hex))
+                .block(Block::new().borders(Borders::all()).title(format!("{} bytes", data.hex.len())))
+                .scroll((data.hex_scroll, 0));
+            frame.render_widget(hex, layout[1]);
+        } else if matches!(data.mode, Mode::Focus) {
+            let lines = data.text.lines();
+            let width = lines.len().to_string().len();
+            let focus = data.focus.iter()
+                .map(|row| format!("{:>width$} {}", row + 1, lines[*row]));
+            let focus = List::new(focus)
+                .block(Block::new().borders(Borders::all()).title("Lines matching the search pattern"))
+                .style(Style::default().fg(Color::LightCyan))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(focus, layout[1], &mut data.focus_state);
+        } else {
+            if data.view.line_numbers {
+                data.text.set_line_number_style(Style::default()
+                    .bg(ratatui::style::Color::DarkGray)
+                    .fg(ratatui::style::Color::White)
+                );
+            } else {
+                data.text.remove_line_number();
+            }
+            let editor = match data.scan.as_mut() {
+                Some(scan) => {
+                    let halves = Layout::horizontal([
+                        Constraint::Percentage(50),
+                        Constraint::Percentage(50),
+                    ]).split(layout[1]);
+                    scan.render(halves[1], frame.buffer_mut());
+                    halves[0]
+                },
+                None => layout[1],
+            };
+            let area = match data.pane.as_mut() {
+                Some(pane) => {
+                    let halves = [Constraint::Percentage(50), Constraint::Percentage(50)];
+                    let halves = if pane.stacked {
+                        Layout::vertical(halves).split(editor)
+                    } else {
+                        Layout::horizontal(halves).split(editor)
+                    };
+                    let (this, other) = if pane.first { (halves[1], halves[0]) } else { (halves[0], halves[1]) };
+                    let modified = |dirty: bool| if dirty { " [modified]" } else { "" };
+                    pane.text.set_block(Block::new().borders(Borders::all())
+                        .title(format!("{:0pad$}{}", pane.x, modified(pane.dirty), pad = data.pad)));
+                    frame.render_widget(pane.text.widget(), other);
+                    data.text.set_block(Block::new().borders(Borders::all())
+                        .border_style(Style::default().fg(Color::Yellow))
+                        .title(format!("{}{}", data.curr.value(), modified(data.dirty))));
+                    this
+                },
+                None => {
+                    data.text.set_block(Block::new().borders(Borders::all()));
+                    editor
+                },
+            };
+            frame.render_widget(data.text.widget(), area);
+            self.viewport.update(&data.text, area);
+            self.decorate(data, frame.buffer_mut());
+            if data.flash.is_some() {
+                frame.buffer_mut().set_style(area, Style::default().add_modifier(Modifier::REVERSED));
+            }
+            // the terminal cursor takes the place of the textarea one so
+            // that its shape tells the mode apart
+            if matches!(data.mode, Mode::Command | Mode::Input | Mode::Selection | Mode::History | Mode::Cuts | Mode::Reflow) {
+                let (row, col) = data.text.cursor();
+                if let Some((x, y)) = self.viewport.position(&data.text.lines()[row], (row, col)) {
+                    frame.set_cursor(x, y);
+                }
+            }
+        }
+
+        let (row, col) = data.text.cursor();
+        let column = data.text.lines().get(row)
+            .map_or(0, |line| graphemes(line, 0..col));
+        let mut position = format!("{}:{}", row + 1, column + 1);
+        if matches!(data.mode, Mode::Selection) {
+            let count = selected(data.text.lines(), data.anchor, (row, col));
+            position.push_str(&format!(" ({count} sel)"));
+        }
+        let position = format!(" {position} ");
+
+        let status_line = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(position.chars().count() as u16),
+            Constraint::Length(10)
+        ]).split(layout[2]);
+
+        let position = Block::new()
+            .style(Style::default().bg(Color::DarkGray).fg(Color::White))
+            .title(position);
+        frame.render_widget(position, status_line[1]);
+        frame.render_widget(mode, status_line[2]);
+
+        if matches!(data.mode, Mode::Log) {
+            let area = popup(layout[1], 80, 60);
+            let log = data.log.iter().rev()
+                .map(|m| m.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let log = Paragraph::new(log)
+                .block(Block::new().borders(Borders::all()).title("Messages (newest first)"))
+                .scroll((data.log_scroll, 0));
+            frame.render_widget(Clear, area);
+            frame.render_widget(log, area);
+        }
+        if matches!(data.mode, Mode::Keys) {
+            let area = popup(layout[1], 60, 80);
+            let mut lines = vec![];
+            for (category, entries) in keys::COMMAND_KEYS.iter() {
+                if !lines.is_empty() {
+                    lines.push(ratatui::text::Line::default());
+                }
+                lines.push(ratatui::text::Line::styled(*category, Style::default().add_modifier(Modifier::BOLD)));
+                lines.extend(entries.iter().map(|(action, key, what)| {
+                    let key = data.keymap.key(action, key);
+                    ratatui::text::Line::raw(format!("  {key:<12} {what:<56} {action}"))
+                }));
+            }
+            let sheet = Paragraph::new(lines)
+                .block(Block::new().borders(Borders::all()).title("Keys (command mode), with their action name for keys.toml"))
+                .scroll((data.keys_scroll, 0));
+            frame.render_widget(Clear, area);
+            frame.render_widget(sheet, area);
+        }
+        if matches!(data.mode, Mode::Diff) {
+            let area = popup(layout[1], 80, 80);
+            let (mut added, mut removed) = (0, 0);
+            let lines = data.changes.iter()
+                .map(|change| match change {
+                    Change::Same(line) => ratatui::text::Line::styled(format!("  {line}"), Style::default().fg(Color::Gray)),
+                    Change::Removed(line) => {
+                        removed += 1;
+                        ratatui::text::Line::styled(format!("- {line}"), Style::default().fg(Color::Red))
+                    },
+                    Change::Added(line) => {
+                        added += 1;
+                        ratatui::text::Line::styled(format!("+ {line}"), Style::default().fg(Color::Green))
+                    },
+                })
+                .collect::<Vec<_>>();
+            let changes = Paragraph::new(lines)
+                .block(Block::new().borders(Borders::all()).title(format!("Changes since the last save (+{added} -{removed})")))
+                .scroll((data.changes_scroll, 0));
+            frame.render_widget(Clear, area);
+            frame.render_widget(changes, area);
+        }
+        if matches!(data.mode, Mode::Message) {
+            let area = popup(layout[1], 60, 40);
+            let msg = Paragraph::new(data.last_msg.as_str())
+                .block(Block::new().borders(Borders::all()).title("Message"))
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: false });
+            frame.render_widget(Clear, area);
+            frame.render_widget(msg, area);
+        }
+        if let Some(Confirm { action: Pending::Columns(_, table), .. }) = data.confirm.as_ref() {
+            let area = popup(layout[1], 80, 60);
+            let preview = table.iter()
+                .map(|row| row.replace('\t', "→"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let preview = Paragraph::new(preview)
+                .block(Block::new().borders(Borders::all()).title("Preview"));
+            frame.render_widget(Clear, area);
+            frame.render_widget(preview, area);
+        }
+        if matches!(data.mode, Mode::Chars) {
+            let area = popup(layout[1], 40, 60);
+            let chars = data.config.special_chars.iter()
+                .map(|c| {
+                    let code = c.chars().map(|c| format!("U+{:04X}", c as u32)).collect::<Vec<_>>().join(" ");
+                    format!("{c:<3} {code}")
+                });
+            let chars = List::new(chars)
+                .block(Block::new().borders(Borders::all()).title("Special characters"))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_widget(Clear, area);
+            frame.render_stateful_widget(chars, area, &mut data.chars);
+        }
+        if matches!(data.mode, Mode::Palette) {
+            let area = popup(layout[1], 60, 80);
+            let commands = data.commands().into_iter()
+                .map(|(_, key, what)| format!("{key:<12} {what}"))
+                .collect::<Vec<_>>();
+            let commands = List::new(commands)
+                .block(Block::new().borders(Borders::all()).title("Commands"))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_widget(Clear, area);
+            frame.render_stateful_widget(commands, area, &mut data.palette_state);
+        }
+        if matches!(data.mode, Mode::Recent) {
+            let area = popup(layout[1], 80, 60);
+            let recent = List::new(data.recent.iter().map(String::as_str))
+                .block(Block::new().borders(Borders::all()).title("Recent directories"))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_widget(Clear, area);
+            frame.render_stateful_widget(recent, area, &mut data.recent_state);
+        }
+
+        match data.mode {
+            Mode::OpenDir => {
+                TextPrompt::from("Open Directory")
+                    .draw(frame, status_line[0], &mut data.cwd);
+            },
+            Mode::OpenFile => {
+                TextPrompt::from("Open File (id or path)")
+                    .draw(frame, status_line[0], &mut data.file);
+            },
+            Mode::Compare => {
+                TextPrompt::from("Compare Files (two ids)")
+                    .draw(frame, status_line[0], &mut data.cmp);
+            },
+            Mode::Flag => {
+                TextPrompt::from("Flag Note")
+                    .draw(frame, status_line[0], &mut data.note);
+            },
+            Mode::Cuts => {
+                let help = match data.config.split.pattern.as_deref() {
+                    Some(pattern) => format!("Split at the matches of {pattern} (Enter to split, Esc to cancel)"),
+                    None => format!("Split at runs of {} spaces (+/- to adjust, Enter to split, Esc to cancel)", data.min_run),
+                };
+                let help = Block::new().title(help)
+                    .style(Style::default().fg(Color::Magenta));
+                frame.render_widget(help, status_line[0]);
+            },
+            Mode::Reflow => {
+                let help = format!("Reflow the paragraph to {} characters (+/- to adjust, Enter to reflow, Esc to cancel)", data.reflow_width);
+                let help = Block::new().title(help)
+                    .style(Style::default().fg(Color::Magenta));
+                frame.render_widget(help, status_line[0]);
+            },
+            Mode::Replace => {
+                TextPrompt::from("Replace With (Enter: next, Shift+Enter/Tab: whole file)")
+                    .draw(frame, status_line[0], &mut data.replacement);
+            },
+            Mode::ReplacePattern => {
+                TextPrompt::from("Replace (all the pages)")
+                    .draw(frame, status_line[0], &mut data.pages_pattern);
+            },
+            Mode::ReplacePages => {
+                TextPrompt::from("Replace With (all the pages)")
+                    .draw(frame, status_line[0], &mut data.replacement);
+            },
+            Mode::Pad => {
+                TextPrompt::from("Pad Width (digits of the page names)")
+                    .draw(frame, status_line[0], &mut data.pad_width);
+            },
+            Mode::Store => {
+                TextPrompt::from("Store In Register")
+                    .draw(frame, status_line[0], &mut data.register);
+            },
+            Mode::Palette => {
+                TextPrompt::from("Command (type to filter)")
+                    .draw(frame, status_line[0], &mut data.palette);
+            },
+            Mode::Recall => {
+                TextPrompt::from("Insert Register")
+                    .draw(frame, status_line[0], &mut data.register);
+            },
+            Mode::Global => {
+                TextPrompt::from("Go To Line (whole collection)")
+                    .draw(frame, status_line[0], &mut data.global);
+            },
+            Mode::Goto => {
+                TextPrompt::from("Go To Line (g: top)")
+                    .draw(frame, status_line[0], &mut data.goto);
+            },
+            Mode::Pane => {
+                TextPrompt::from("Split With (id only)")
+                    .draw(frame, status_line[0], &mut data.pane_id);
+            },
+            Mode::NewPage => {
+                TextPrompt::from("New Page (id only)")
+                    .draw(frame, status_line[0], &mut data.new_page);
+            },
+            Mode::SaveAs => {
+                TextPrompt::from("Save As (id only)")
+                    .draw(frame, status_line[0], &mut data.save_as);
+            },
+            Mode::Search => {
+                let modifiers = [(data.search_ci, "ignore case"), (data.search_word, "whole word")]
+                    .into_iter()
+                    .filter_map(|(on, name)| on.then_some(name))
+                    .collect::<Vec<_>>();
+                let counter = match data.text.search_pattern() {
+                    None => String::new(),
+                    Some(pattern) => match match_counter(data.text.lines(), pattern, data.text.cursor()) {
+                        (_, 0)          => " no match".to_string(),
+                        (Some(x), tot)  => format!(" {x}/{tot}"),
+                        (None, tot)     => format!(" -/{tot}"),
+                    },
+                };
+                let title = if modifiers.is_empty() {
+                    format!("Search Pattern{counter} (alt+c: ignore case, alt+w: whole word)")
+                } else {
+                    format!("Search Pattern{counter} [{}]", modifiers.join(", "))
+                };
+                TextPrompt::from(title)
+                    .draw(frame, status_line[0], &mut data.srch);
+            },
+            Mode::Confirm => {
+                let question = data.confirm.as_ref()
+                    .map(|c| format!("{} (y/n)", c.question))
+                    .unwrap_or_default();
+                let question = Block::new().title(question)
+                    .style(Style::default().fg(Color::Yellow));
+                frame.render_widget(question, status_line[0]);
+            },
+            _ => {
+                if data.msg.is_empty() {
+                    let ratio = match data.gauge {
+                        Gauge::Collection => {
+                            let cur: u32 = data.curr.value().parse().unwrap();
+                            (1 + cur) as f64 / (1 + data.tot) as f64
+                        },
+                        Gauge::File => {
+                            let (row, _) = data.text.cursor();
+                            (1 + row) as f64 / data.text.lines().len() as f64
+                        },
+                    };
+                    let (words, chars) = *data.counts.get_or_insert_with(|| counts(data.text.lines()));
+                    let counts = format!(" {words} words, {chars} chars ");
+                    let gauge = Layout::horizontal([
+                        Constraint::Min(0),
+                        Constraint::Length(counts.chars().count() as u16),
+                    ]).split(status_line[0]);
+                    let progress = LineGauge::default().ratio(ratio.min(1.0));
+                    frame.render_widget(progress, gauge[0]);
+                    frame.render_widget(Block::new().title(counts), gauge[1]);
+                } else {
+                    let msg = Block::new().title(data.msg.as_str())
+                        .style(Style::default().fg(Color::Red));
+                    frame.render_widget(msg, status_line[0]);
+                }
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The editor working in a new directory which holds the given files
+    fn state(name: &str, fnames: &[&str]) -> AppState<'static> {
+        let dir = files::test_dir(name);
+        for fname in fnames {
+            std::fs::write(dir.join(fname), "text").unwrap();
+        }
+        AppState { data: Data::new(Config::default(), &dir), view: View::new() }
+    }
+
+    /// The given lines as those of a buffer
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn loading_over_unsaved_changes_asks_first() {
+        let mut state = state("dirty", &["000.txt", "001.txt"]);
+        state.load_first().unwrap();
+        state.data.text.insert_str("edit");
+        state.modified();
+        state.next().unwrap();
+        assert_eq!(state.data.curr.value(), "000");
+        assert!(matches!(state.data.confirm, Some(Confirm { action: Pending::Load(1), .. })));
+        state.confirm_input(Event::Key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))).unwrap();
+        assert_eq!(state.data.curr.value(), "001");
+        assert!(!state.data.dirty);
+    }
+
+    #[test]
+    fn prev_stops_at_the_first_page() {
+        let mut state = state("prev", &["001.txt", "002.txt", "000.png"]);
+        state.load_first().unwrap();
+        state.prev().unwrap();
+        assert_eq!(state.data.curr.value(), "001");
+        assert_eq!(state.data.msg, "Already at first file");
+    }
+
+    #[test]
+    fn next_stops_at_the_last_page() {
+        let mut state = state("next", &["001.txt", "002.txt", "001.png", "002.png", "export.md"]);
+        state.load_first().unwrap();
+        state.next().unwrap();
+        assert_eq!(state.data.curr.value(), "002");
+        state.next().unwrap();
+        assert_eq!(state.data.curr.value(), "002");
+        assert_eq!(state.data.msg, "Already at last file");
+    }
+
+    #[test]
+    fn invalid_utf8_is_loaded_lossily() {
+        let mut state = state("utf8", &[]);
+        let fname = PathBuf::from(state.data.cwd.value()).join("000.txt");
+        std::fs::write(&fname, b"caf\xFF\nok").unwrap();
+        state.load(0).unwrap();
+        assert_eq!(state.data.text.lines(), ["caf\u{fffd}", "ok"]);
+        assert_eq!(state.data.msg, "000 is not valid UTF-8, the invalid bytes are shown as \u{fffd}");
+    }
+
+    #[test]
+    fn the_pages_are_named_after_the_detected_width() {
+        for (width, first, second) in [(3, "009.txt", "010.txt"), (4, "0009.txt", "0010.txt"), (5, "00009.txt", "00010.txt")] {
+            let mut state = state(&format!("pad{width}"), &[first, second]);
+            state.load_first().unwrap();
+            assert_eq!(state.data.pad, width);
+            assert_eq!(state.data.curr.value(), &first[..width]);
+            state.next().unwrap();
+            assert_eq!(state.data.curr.value(), &second[..width]);
+            assert!(state.fname(10).ends_with(second));
+            assert_eq!(state.id(7), format!("{}7", "0".repeat(width - 1)));
+        }
+    }
+
+    #[test]
+    fn the_runs_of_blank_lines_are_collapsed() {
+        assert_eq!(squeeze_blank_lines(&lines(&["a", "", "", "b", "", "c"])), lines(&["a", "", "b", "", "c"]));
+        // the lines made of whitespace only count as blank
+        assert_eq!(squeeze_blank_lines(&lines(&["a", " ", "\t", "", "b"])), lines(&["a", "", "b"]));
+    }
+
+    #[test]
+    fn the_leading_and_trailing_runs_are_collapsed_not_removed() {
+        assert_eq!(squeeze_blank_lines(&lines(&["", "", "a", "", "", ""])), lines(&["", "a", ""]));
+        assert_eq!(squeeze_blank_lines(&lines(&["", "a", ""])), lines(&["", "a", ""]));
+        assert_eq!(squeeze_blank_lines(&lines(&["", "", ""])), lines(&[""]));
+    }
+
+    #[test]
+    fn ctrl_w_switches_to_the_other_pane() {
+        let mut state = state("pane", &["000.txt", "001.txt"]);
+        state.load_first().unwrap();
+        *state.data.pane_id.value_mut() = "001".to_string();
+        state.pane_input(Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))).unwrap();
+        let ctrl_w = Event::Key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        state.command_input(ctrl_w).unwrap();
+        assert_eq!(state.data.curr.value(), "001");
+        assert_eq!(state.data.pane.as_ref().map(|pane| pane.x), Some(0));
+    }
+
+    #[test]
+    fn the_search_starts_from_the_cursor() {
+        let mut state = state("search", &[]);
+        state.data.text = textarea(lines(&["a x", "b", "c x"]), "");
+        state.data.text.move_cursor(tui_textarea::CursorMove::Jump(1, 0));
+        *state.data.srch.value_mut() = String::new();
+        let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        state.command_input(key('/')).unwrap();
+        state.search_input(key('x')).unwrap();
+        assert_eq!(state.data.text.cursor(), (2, 2));
+    }
+
+    #[test]
+    fn the_stacked_split_slides_along() {
+        let mut state = state("slide", &["000.txt", "001.txt", "002.txt"]);
+        state.load_first().unwrap();
+        state.split_adjacent().unwrap();
+        state.next().unwrap();
+        assert_eq!(state.data.curr.value(), "001");
+        state.data.text.insert_str("edit");
+        state.modified();
+        state.next().unwrap();
+        assert_eq!(state.data.curr.value(), "002");
+        let pane = state.data.pane.as_ref().unwrap();
+        assert_eq!((pane.x, pane.first, pane.dirty), (1, true, true));
+    }
+
+    #[test]
+    fn next_skips_the_missing_pages() {
+        let mut state = state("gap", &["000.txt", "003.txt"]);
+        state.load_first().unwrap();
+        state.next().unwrap();
+        assert_eq!(state.data.curr.value(), "003");
+        state.prev().unwrap();
+        assert_eq!(state.data.curr.value(), "000");
+    }
+}
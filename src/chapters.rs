@@ -0,0 +1,85 @@
+//! This module implements the reorganization of a collection into chapters.
+//! The chapters are defined in a TOML file of the directory which maps the
+//! number of the first page of each chapter to its name, e.g.
+//!
+//! ```toml
+//! 0  = "preface"
+//! 12 = "chapter-1"
+//! ```
+//!
+//! The pages of each chapter are then moved to a subdirectory named after
+//! it, where they are numbered from 0 again (padded like the original pages).
+
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+use crate::errors::Result;
+
+/// A page to move into its chapter
+#[derive(Debug, Clone)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to  : PathBuf,
+}
+
+/// Reads the chapter definitions of the directory
+fn definitions(dir: &Path, file: &str) -> Result<BTreeMap<u32, String>> {
+    let text = std::fs::read_to_string(dir.join(file))?;
+    let raw: BTreeMap<String, String> = toml::from_str(&text)?;
+    let mut chapters = BTreeMap::new();
+    for (start, name) in raw {
+        let start = start.parse()?;
+        let mut parts = Path::new(&name).components();
+        if !matches!((parts.next(), parts.next()), (Some(Component::Normal(_)), None)) {
+            let msg = format!("invalid chapter name {name:?} (it must be a plain directory name)");
+            return Err(IoError::new(ErrorKind::InvalidData, msg).into());
+        }
+        chapters.insert(start, name);
+    }
+    Ok(chapters)
+}
+
+/// Computes where each page goes (dry run: nothing gets moved). The pages
+/// before the first chapter stay where they are, and the moved ones are
+/// named with `pad` digits. This fails when any destination already exists,
+/// so that nothing is ever overwritten.
+pub fn plan(dir: &Path, file: &str, pad: usize) -> Result<Vec<Move>> {
+    let chapters = definitions(dir, file)?;
+    let mut pages = read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "txt"))
+        .filter_map(|p| Some((p.file_stem()?.to_str()?.parse::<u32>().ok()?, p)))
+        .collect::<Vec<_>>();
+    pages.sort();
+
+    let mut moves = vec![];
+    let mut numbers: BTreeMap<&str, u32> = BTreeMap::new();
+    for (x, from) in pages {
+        let Some((_, name)) = chapters.range(..=x).next_back() else {
+            continue;
+        };
+        let number = numbers.entry(name.as_str()).or_default();
+        let to = dir.join(name).join(format!("{:0pad$}.txt", *number));
+        *number += 1;
+        if to.exists() {
+            let msg = format!("{} already exists", to.display());
+            return Err(IoError::new(ErrorKind::AlreadyExists, msg).into());
+        }
+        moves.push(Move { from, to });
+    }
+    Ok(moves)
+}
+
+/// Moves the pages into their chapters
+pub fn apply(moves: &[Move]) -> Result<()> {
+    for Move { from, to } in moves {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(from, to)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! This module abstracts the access to the clipboard, which can either be
+//! the system clipboard, the terminal (through the OSC 52 escape sequence,
+//! which also works over SSH) or a register internal to the application.
+
+use std::io::{stdout, Write};
+
+use clipboard::{ClipboardContext, ClipboardProvider};
+use serde::Deserialize;
+
+use crate::errors::{Error, Result};
+
+/// Something text can be copied to (and pasted from)
+pub trait Clipboard {
+    /// Replaces the content of the clipboard
+    fn set(&mut self, text: String) -> Result<()>;
+    /// The text held by the clipboard, None when it is empty, holds no text
+    /// or cannot be read
+    fn get(&mut self) -> Option<String>;
+}
+
+/// The kinds of clipboard that can be selected in the configuration
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardKind {
+    #[default]
+    System,
+    Osc52,
+    Register,
+}
+
+impl ClipboardKind {
+    /// Creates a clipboard of this kind
+    pub fn open(self) -> Box<dyn Clipboard> {
+        match self {
+            ClipboardKind::System   => Box::new(System),
+            ClipboardKind::Osc52    => Box::new(Osc52),
+            ClipboardKind::Register => Box::<Register>::default(),
+        }
+    }
+}
+
+/// The clipboard of the system
+pub struct System;
+
+impl Clipboard for System {
+    fn set(&mut self, text: String) -> Result<()> {
+        // the clipboard crate returns a Box<dyn Error>, which does not
+        // implement Error itself, hence the errors are kept as text
+        let mut clipboard: ClipboardContext = ClipboardProvider::new()
+            .map_err(|e| Error::Clipboard(e.to_string()))?;
+        clipboard.set_contents(text)
+            .map_err(|e| Error::Clipboard(e.to_string()))
+    }
+    fn get(&mut self) -> Option<String> {
+        let mut clipboard: ClipboardContext = ClipboardProvider::new().ok()?;
+        clipboard.get_contents().ok().filter(|text| !text.is_empty())
+    }
+}
+
+/// The clipboard of the terminal, which can only be written to
+pub struct Osc52;
+
+impl Clipboard for Osc52 {
+    fn set(&mut self, text: String) -> Result<()> {
+        let mut out = stdout();
+        write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+        out.flush()?;
+        Ok(())
+    }
+    fn get(&mut self) -> Option<String> {
+        None
+    }
+}
+
+/// A clipboard that lives in the application only
+#[derive(Default)]
+pub struct Register(String);
+
+impl Clipboard for Register {
+    fn set(&mut self, text: String) -> Result<()> {
+        self.0 = text;
+        Ok(())
+    }
+    fn get(&mut self) -> Option<String> {
+        Some(self.0.clone()).filter(|text| !text.is_empty())
+    }
+}
+
+/// Standard base64 encoding (with padding) as expected by OSC 52
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,416 @@
+//! This module defines the user configuration of the application. The
+//! configuration is read from `$XDG_CONFIG_HOME/couic/config.toml` (or
+//! `~/.config/couic/config.toml`) and every setting falls back to its
+//! default value when it is absent.
+
+use std::collections::HashMap;
+use std::env::var_os;
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use crate::clip::ClipboardKind;
+use crate::errors::Result;
+use crate::keys::KeyBinding;
+use crate::session::ViewSettings;
+
+/// The complete configuration of the application
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// How the collection gets exported
+    pub export: ExportConfig,
+    /// File where the scratch buffer is kept between sessions (if any)
+    pub scratch_file: Option<PathBuf>,
+    /// Number of seconds after which an unanswered y/n prompt is cancelled
+    /// (prompts wait forever when absent)
+    pub confirm_timeout: Option<u64>,
+    /// Number of status messages kept in the message log
+    pub log_size: usize,
+    /// Characters offered by the special character picker
+    pub special_chars: Vec<String>,
+    /// Lines longer than this (in characters) are flagged
+    pub max_line_length: Option<usize>,
+    /// Width (in characters) the paragraphs get reflowed to
+    pub reflow_width: usize,
+    /// Number of seconds between two counts of the pages of the directory
+    /// (the directory is only counted when it is opened when absent)
+    pub reload_interval: Option<u64>,
+    /// Number of seconds between two automatic saves of the buffer (when it
+    /// has unsaved changes), which is then also saved before moving to the
+    /// next or previous page. There is no automatic save when absent
+    pub autosave: Option<u64>,
+    /// Opening a directory with more pages than this asks for a confirmation
+    /// and offers the lazy mode, where the pages are neither counted again
+    /// periodically nor scanned for those left to transcribe
+    pub huge_directory: Option<usize>,
+    /// What Enter does in command mode
+    pub enter: EnterAction,
+    /// How long lines get split
+    pub split: SplitConfig,
+    /// How to cycle through the editable modes
+    pub cycle: CycleConfig,
+    /// The colors of the interface
+    pub theme: Theme,
+    /// Convert every kind of line break (\r\n, \r, U+2028, ...) to \n when a
+    /// file is loaded
+    pub normalize_newlines: bool,
+    /// The smallest terminal (columns, rows) in which the interface is drawn
+    pub min_size: (u16, u16),
+    /// Where the text gets copied: system, osc52 or register
+    pub clipboard: ClipboardKind,
+    /// The replacement applied to every match of the search pattern by the
+    /// markup command ($0 stands for the whole match, $1 for the first group)
+    pub markup: String,
+    /// The modes (lowercase names) from which ctrl+q quits right away. From
+    /// any other mode, ctrl+q asks for a confirmation first
+    pub quit_from: Vec<String>,
+    /// The view settings used in the directories where they have not been
+    /// toggled yet
+    pub view: ViewSettings,
+    /// The delimiter that replaces the runs of spaces separating the
+    /// columns of an aligned table
+    pub delimiter: String,
+    /// The abbreviations after which the capitalization of sentence starts
+    /// leaves the next word alone
+    pub abbreviations: Vec<String>,
+    /// The progress shown by the gauge of the status line at startup
+    pub gauge: Gauge,
+    /// Remove the trailing whitespace of the lines and the trailing blank
+    /// lines of the file each time it is saved
+    pub trim_on_save: bool,
+    /// The extensions of the scanned pages (e.g. 012.png) which are paired
+    /// with the text files
+    pub image_extensions: Vec<String>,
+    /// Copy the pages to .NNN.txt.bak before a replacement across the
+    /// directory modifies them
+    pub backup: bool,
+    /// The shape of the cursor in each mode
+    pub cursor: Cursors,
+    /// The least number of non-blank characters of a page for it to count
+    /// as transcribed when checking whether the collection is complete
+    pub min_chars: usize,
+    /// Keep moving while a movement key is held. Turn it off for terminals
+    /// which send a repeat along with the press of each stroke
+    pub key_repeat: bool,
+    /// The pattern highlighted in every loaded file (by default, the page
+    /// and folio markers)
+    pub highlight: String,
+    /// Keep highlighting the last search pattern in the files loaded next.
+    /// When off, loading a file brings the highlight pattern back
+    pub persistent_search: bool,
+    /// Bring the search pattern back to the highlight pattern (that of the
+    /// directory when its session defines one) each time another directory
+    /// is opened. When off, the search pattern is carried over
+    pub reset_search: bool,
+    /// Number of search patterns recalled with up and down in search mode
+    pub search_history: usize,
+    /// Keep the search patterns between sessions (in the configuration
+    /// directory)
+    pub persist_search_history: bool,
+    /// Load the lowest-numbered page of the directory on startup. When not
+    /// set, this is only done when a directory is given on the command line
+    pub autoload: Option<bool>,
+    /// What to do when the current directory cannot be accessed (e.g. it
+    /// was deleted): use the home directory or ask for another one
+    pub cwd_fallback: CwdFallback,
+    /// The number of digits of the page names (e.g. 3 for 001.txt). When not
+    /// set, it is detected from the pages of each directory that is opened
+    pub pad: Option<usize>,
+    /// The file of the directory which defines its chapters (their first
+    /// page and their name)
+    pub chapters: String,
+    /// Start without the title bar and the status line (the status line
+    /// still shows up for the messages and prompts)
+    pub distraction_free: bool,
+    /// The marker prefixed to each line of the selection copied as a quote
+    pub quote: String,
+    /// What saving an empty buffer does: write an empty file, delete the
+    /// file or refuse to save
+    pub empty_save: EmptySave,
+    /// The cue given when moving to the next (or previous) file is not
+    /// possible because the end (or start) of the collection is reached
+    pub boundary_cue: Cue,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            export: ExportConfig::default(),
+            scratch_file: None,
+            confirm_timeout: None,
+            log_size: 100,
+            special_chars: ["—", "–", "\u{a0}", "§", "¶", "…", "«", "»", "†", "‡", "°", "½"]
+                .iter().map(|c| c.to_string()).collect(),
+            max_line_length: None,
+            reflow_width: 80,
+            reload_interval: None,
+            autosave: None,
+            huge_directory: Some(5000),
+            enter: EnterAction::Next,
+            split: SplitConfig::default(),
+            cycle: CycleConfig::default(),
+            theme: Theme::default(),
+            normalize_newlines: true,
+            min_size: (30, 6),
+            clipboard: ClipboardKind::default(),
+            markup: "[$0]".to_string(),
+            quit_from: vec!["command".to_string()],
+            view: ViewSettings::default(),
+            delimiter: "\t".to_string(),
+            abbreviations: ["Mr.", "Mrs.", "Dr.", "St.", "cf.", "e.g.", "i.e.", "etc.", "vs.", "p.", "vol."]
+                .iter().map(|a| a.to_string()).collect(),
+            gauge: Gauge::Collection,
+            trim_on_save: false,
+            image_extensions: ["png", "jpg", "jpeg", "tif", "tiff"]
+                .iter().map(|e| e.to_string()).collect(),
+            backup: false,
+            cursor: Cursors::default(),
+            min_chars: 1,
+            key_repeat: true,
+            highlight: r"\d+|f\.|fol|p\.|page|scan".to_string(),
+            persistent_search: true,
+            reset_search: false,
+            search_history: 50,
+            persist_search_history: true,
+            autoload: None,
+            cwd_fallback: CwdFallback::Home,
+            pad: None,
+            chapters: "chapters.toml".to_string(),
+            distraction_free: false,
+            quote: "> ".to_string(),
+            empty_save: EmptySave::Write,
+            boundary_cue: Cue::None,
+        }
+    }
+}
+
+/// The settings of the concatenation export
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// Name of the exported document (relative to the current directory)
+    pub output: String,
+    /// Name of the exported Markdown document
+    pub markdown: String,
+    /// Start the Markdown document with a table of contents of the headings
+    pub toc: bool,
+    /// Name of the review report listing the flagged files
+    pub flags: String,
+    /// Template written before the content of each page
+    pub header: String,
+    /// Template written after the content of each page
+    pub footer: String,
+    /// The line marking a page break in the text. It becomes a form-feed
+    /// in the exported document
+    pub page_break: String,
+}
+
+/// The settings of the long lines splitter
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SplitConfig {
+    /// Lines matching this pattern (e.g. tables) are never split
+    pub keep: Option<String>,
+    /// Lines longer than this (in bytes) are left untouched
+    pub guard: usize,
+    /// The shortest run of whitespace a line gets split at
+    pub min_run: usize,
+    /// The pattern a line gets split at, instead of the runs of whitespace
+    pub pattern: Option<String>,
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        Self { keep: None, guard: 64 * 1024, min_run: 3, pattern: None }
+    }
+}
+
+/// The colors of the interface
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// The color of the mode indicator, indexed by the (lowercase) name of
+    /// the mode, e.g. `input = "green"`
+    pub modes: HashMap<String, Color>,
+    /// The background of the trailing whitespace when it is highlighted
+    pub trailing: Option<Color>,
+}
+
+impl Theme {
+    /// The background of the trailing whitespace
+    pub fn trailing(&self) -> Color {
+        self.trailing.unwrap_or(Color::LightRed)
+    }
+
+    /// The color of the indicator of the given mode
+    pub fn mode(&self, name: &str) -> Color {
+        let name = name.to_lowercase();
+        self.modes.get(&name).copied().unwrap_or(match name.as_str() {
+            "input"   => Color::Green,
+            "overwrite" => Color::LightGreen,
+            "search"  => Color::Yellow,
+            "select"  => Color::Magenta,
+            "command" => Color::Blue,
+            "confirm" | "quit" => Color::Red,
+            _ => Color::Gray,
+        })
+    }
+}
+
+/// The shapes of the cursor
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Cursors {
+    /// The shape of the cursor indexed by the (lowercase) name of the mode,
+    /// e.g. `input = "bar"`
+    pub modes: HashMap<String, CursorShape>,
+}
+
+impl Cursors {
+    /// The shape of the cursor in the given mode
+    pub fn mode(&self, name: &str) -> CursorShape {
+        let name = name.to_lowercase();
+        self.modes.get(&name).copied().unwrap_or(match name.as_str() {
+            "input" | "search" => CursorShape::Bar,
+            "select" | "overwrite" => CursorShape::Underline,
+            _ => CursorShape::Block,
+        })
+    }
+}
+
+/// The shape of the terminal cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CursorShape {
+    Block,
+    Underline,
+    Bar,
+}
+
+/// The keys cycling through the editable modes, and the order of the cycle
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CycleConfig {
+    pub forward : KeyBinding,
+    pub backward: KeyBinding,
+    pub modes   : Vec<EditMode>,
+}
+
+impl Default for CycleConfig {
+    fn default() -> Self {
+        Self {
+            forward : KeyBinding::new(KeyCode::F(2), KeyModifiers::NONE),
+            backward: KeyBinding::new(KeyCode::F(2), KeyModifiers::SHIFT),
+            modes   : vec![EditMode::Input, EditMode::Search, EditMode::Selection],
+        }
+    }
+}
+
+/// The modes in which the buffer (or the search pattern) gets edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EditMode {
+    Input,
+    Search,
+    Selection,
+}
+
+/// The actions that can be bound to Enter in command mode
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnterAction {
+    /// Do nothing
+    None,
+    /// Go to the next file (default)
+    Next,
+    /// Save the current file
+    Save,
+    /// Save the current file then go to the next one
+    SaveNext,
+}
+
+/// What happens when an empty buffer gets saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmptySave {
+    /// Write an empty file, e.g. a blank page (default)
+    Write,
+    /// Delete the file
+    Delete,
+    /// Leave the file alone and warn
+    Refuse,
+}
+
+/// A cue drawing the attention of the user, besides the status message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cue {
+    /// No cue, only the message (default)
+    None,
+    /// Briefly show the text in reverse video
+    Flash,
+    /// Ring the terminal bell
+    Bell,
+}
+
+/// The directory used when the current one cannot be accessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CwdFallback {
+    /// The home directory of the user (default)
+    Home,
+    /// The directory chosen in the open-dir prompt
+    Prompt,
+}
+
+/// The progress shown by the gauge of the status line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Gauge {
+    /// The position of the current file in the collection (default)
+    Collection,
+    /// The position of the cursor in the current file
+    File,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            output: "export.txt".to_string(),
+            markdown: "export.md".to_string(),
+            toc: true,
+            flags: "flags.txt".to_string(),
+            header: String::new(),
+            footer: String::new(),
+            page_break: "<pagebreak>".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from disk. A missing file is not an error
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.is_file() => {
+                let text = std::fs::read_to_string(path)?;
+                Ok(toml::from_str(&text)?)
+            },
+            _ => Ok(Self::default())
+        }
+    }
+
+    /// The location of the configuration directory
+    pub fn dir() -> Option<PathBuf> {
+        var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("couic"))
+    }
+
+    /// The location of the configuration file
+    pub fn path() -> Option<PathBuf> {
+        Self::dir().map(|dir| dir.join("config.toml"))
+    }
+}
@@ -0,0 +1,56 @@
+//! This module compares two versions of a text line by line, e.g. a page as
+//! it was saved and as it is in the buffer.
+
+/// Beyond this many (lines x lines) left to compare, the lines that differ
+/// are simply reported as removed then added rather than matched
+const MAX_CELLS: usize = 4_000_000;
+
+/// A line of the comparison
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A line found in both versions
+    Same(String),
+    /// A line of the old version only
+    Removed(String),
+    /// A line of the new version only
+    Added(String),
+}
+
+/// The lines of both versions, in order, telling which ones were removed
+/// from `old` and added in `new`. The lines kept are those of a longest
+/// common subsequence of the two.
+pub fn diff(old: &[String], new: &[String]) -> Vec<Change> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut changes = old[..prefix].iter().cloned().map(Change::Same).collect::<Vec<_>>();
+    if a.len().saturating_mul(b.len()) > MAX_CELLS {
+        changes.extend(a.iter().cloned().map(Change::Removed));
+        changes.extend(b.iter().cloned().map(Change::Added));
+    } else {
+        // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+        let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                changes.push(Change::Same(a[i].clone()));
+                i += 1;
+                j += 1;
+            } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                changes.push(Change::Removed(a[i].clone()));
+                i += 1;
+            } else {
+                changes.push(Change::Added(b[j].clone()));
+                j += 1;
+            }
+        }
+    }
+    changes.extend(old[old.len() - suffix..].iter().cloned().map(Change::Same));
+    changes
+}
@@ -12,6 +12,14 @@ pub enum Error {
     ParseInt(#[from] ParseIntError),
     #[error("regex error {0}")]
     Regex(#[from] regex::Error),
+    #[error("config error {0}")]
+    Config(#[from] toml::de::Error),
+    #[error("cannot serialize {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("clipboard error {0}")]
+    Clipboard(String),
+    #[error("image error {0}")]
+    Image(#[from] image::ImageError),
 }
 
 /// Easy result redefinition
@@ -0,0 +1,120 @@
+//! This module implements the export of a whole collection into one single
+//! document.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ExportConfig;
+use crate::errors::Result;
+
+/// Concatenates the content of all the given pages. Each page is wrapped in
+/// the header and footer templates of the configuration, where `{page}` is
+/// replaced by the page id (as in its file name) and `{date}` by the current
+/// date. The page-break markers are turned into form-feeds.
+pub fn concatenate(pages: &[(u32, PathBuf)], config: &ExportConfig) -> Result<String> {
+    let date = today();
+    let mut out = String::new();
+    for (x, path) in pages {
+        let page = id(*x, path);
+        let text = std::fs::read_to_string(path)?;
+        out.push_str(&fill(&config.header, &page, &date));
+        for line in text.split_inclusive('\n') {
+            if !config.page_break.is_empty() && line.trim() == config.page_break {
+                out.push('\x0c');
+                out.push_str(&line[line.trim_end().len()..]);
+            } else {
+                out.push_str(line);
+            }
+        }
+        if !text.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&fill(&config.footer, &page, &date));
+    }
+    Ok(out)
+}
+
+/// The id of a page as it is written in its file name (whatever its padding)
+fn id(x: u32, path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or_else(|| x.to_string(), |stem| stem.to_string())
+}
+
+/// Concatenates the content of all the given pages into a Markdown document.
+/// A comment marks the start of each page, the page-break markers become
+/// CSS page breaks and (unless disabled) the document starts with a table
+/// of contents linking to the `###` headings.
+pub fn markdown(pages: &[(u32, PathBuf)], config: &ExportConfig) -> Result<String> {
+    let mut toc   = String::new();
+    let mut body  = String::new();
+    let mut slugs = HashMap::new();
+    for (x, path) in pages {
+        let text = std::fs::read_to_string(path)?;
+        body.push_str(&format!("<!-- page {} -->\n", id(*x, path)));
+        for line in text.lines() {
+            if !config.page_break.is_empty() && line.trim() == config.page_break {
+                body.push_str(r#"<div style="page-break-after: always"></div>"#);
+            } else {
+                if let Some(title) = heading(line) {
+                    let anchor = slug(title, &mut slugs);
+                    toc.push_str(&format!("- [{title}](#{anchor})\n"));
+                    body.push_str(&format!("<a id=\"{anchor}\"></a>\n\n"));
+                }
+                body.push_str(line);
+            }
+            body.push('\n');
+        }
+        body.push('\n');
+    }
+    if config.toc && !toc.is_empty() {
+        Ok(format!("{toc}\n{body}"))
+    } else {
+        Ok(body)
+    }
+}
+
+/// The title of a `###` heading (the bare separators have none)
+fn heading(line: &str) -> Option<&str> {
+    let title = line.strip_prefix("###")?.trim_start_matches('#').trim();
+    (!title.is_empty()).then_some(title)
+}
+
+/// A unique anchor name for the given title, e.g. `chapter-1` then
+/// `chapter-1-2` when two headings share the same title
+fn slug(title: &str, seen: &mut HashMap<String, usize>) -> String {
+    let slug = title.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let count = seen.entry(slug.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 { slug } else { format!("{slug}-{count}") }
+}
+
+/// Replaces the placeholders of the template
+fn fill(template: &str, page: &str, date: &str) -> String {
+    template
+        .replace("{page}", page)
+        .replace("{date}", date)
+}
+
+/// The current (UTC) date formatted as yyyy-mm-dd
+fn today() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    // civil from days (see http://howardhinnant.github.io/date_algorithms.html)
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{y:04}-{m:02}-{d:02}")
+}
@@ -0,0 +1,81 @@
+//! This module gathers the file operations shared by the editor and the
+//! commands which rewrite the pages in batch.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::errors::Result;
+
+/// Writes the given bytes to the file. They are first written to a temporary
+/// sibling (.NNN.txt.tmp) which then replaces the file, so that a crash never
+/// leaves it half written.
+pub fn write(fname: &Path, bytes: &[u8]) -> Result<()> {
+    write_with(fname, |wrt| wrt.write_all(bytes))
+}
+
+/// Replaces the file with what the function writes to the temporary file.
+/// When it fails, the temporary file is removed and the file left untouched.
+fn write_with(fname: &Path, fill: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>) -> Result<()> {
+    let name = fname.file_name().unwrap_or_default().to_string_lossy();
+    let temp = fname.with_file_name(format!(".{name}.tmp"));
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&temp)?;
+
+    let mut wrt = BufWriter::new(file);
+    let written = fill(&mut wrt)
+        .and_then(|_| wrt.flush())
+        .and_then(|_| wrt.get_ref().sync_all());
+    if let Err(e) = written {
+        std::fs::remove_file(&temp).ok();
+        return Err(e.into());
+    }
+    std::fs::rename(&temp, fname)?;
+
+    Ok(())
+}
+
+/// Creates a new empty directory (in the temporary directory of the system)
+/// for a test to work in
+#[cfg(test)]
+pub fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("couic-{}-{name}", std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_failed_write_leaves_the_file_intact() {
+        let dir = test_dir("write");
+        let fname = dir.join("001.txt");
+        std::fs::write(&fname, "original").unwrap();
+        let failed = write_with(&fname, |wrt| {
+            wrt.write_all(b"partial")?;
+            wrt.flush()?;
+            Err(std::io::Error::other("disk full"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_to_string(&fname).unwrap(), "original");
+        assert!(!dir.join(".001.txt.tmp").exists());
+    }
+
+    #[test]
+    fn a_write_replaces_the_file() {
+        let dir = test_dir("replace");
+        let fname = dir.join("001.txt");
+        std::fs::write(&fname, "original").unwrap();
+        write(&fname, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&fname).unwrap(), "new");
+        assert!(!dir.join(".001.txt.tmp").exists());
+    }
+}
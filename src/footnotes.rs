@@ -0,0 +1,111 @@
+//! This module implements the footnotes: markers `[^n]` placed in the text
+//! which refer to the notes `[^n]: ...` collected at the end of the file.
+
+use std::collections::HashMap;
+
+use lazy_static::*;
+use regex::Regex;
+
+/// Placeholder of the marker being inserted until it gets its number
+const NEW: char = '\u{1}';
+
+lazy_static!{
+    static ref MARKER: Regex = Regex::new(r"\[\^(\d+)\]|\x01").unwrap();
+    static ref NOTE  : Regex = Regex::new(r"^\[\^(\d+)\]:\s?(.*)$").unwrap();
+}
+
+/// Inserts a new footnote marker at the given (row, column) position and
+/// renumbers all the markers in their order of appearance. The notes are
+/// rewritten in the same order at the end of the file, the new one being
+/// empty. Returns the new lines along with the position of the new note.
+pub fn insert(lines: &[String], (row, col): (usize, usize)) -> (Vec<String>, (usize, usize)) {
+    let mut notes = HashMap::new();
+    let mut body  = vec![];
+    let mut cursor = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(caps) = NOTE.captures(line) {
+            let n: u32 = caps[1].parse().unwrap_or_default();
+            notes.entry(n).or_insert_with(|| caps[2].to_string());
+        } else {
+            if i == row {
+                cursor = body.len();
+                let at = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+                let mut line = line.clone();
+                line.insert(at, NEW);
+                body.push(line);
+            } else {
+                body.push(line.clone());
+            }
+        }
+    }
+    while body.last().is_some_and(|l| l.is_empty()) && body.len() > cursor + 1 {
+        body.pop();
+    }
+
+    // old number of each new note (None for the inserted one)
+    let mut order: Vec<Option<u32>> = vec![];
+    let mut numbers = HashMap::new();
+    let mut added = 0;
+    for line in body.iter_mut() {
+        let mut out = String::new();
+        let mut last = 0;
+        for caps in MARKER.captures_iter(line) {
+            let m = caps.get(0).unwrap();
+            out.push_str(&line[last..m.start()]);
+            last = m.end();
+            let k = match caps.get(1).and_then(|n| n.as_str().parse().ok()) {
+                Some(old) => *numbers.entry(old).or_insert_with(|| {
+                    order.push(Some(old));
+                    order.len()
+                }),
+                None => {
+                    order.push(None);
+                    added = order.len();
+                    added
+                },
+            };
+            out.push_str(&format!("[^{k}]"));
+        }
+        out.push_str(&line[last..]);
+        *line = out;
+    }
+    // notes whose marker has disappeared are kept rather than lost
+    let mut orphans = notes.keys().filter(|n| !numbers.contains_key(n)).copied().collect::<Vec<_>>();
+    orphans.sort_unstable();
+    order.extend(orphans.into_iter().map(Some));
+
+    if !order.is_empty() {
+        body.push(String::new());
+    }
+    let mut note = body.len();
+    for (i, old) in order.iter().enumerate() {
+        let text = old.and_then(|old| notes.get(&old)).map(|t| t.as_str()).unwrap_or_default();
+        if i + 1 == added {
+            note = body.len();
+        }
+        body.push(format!("[^{}]: {text}", i + 1));
+    }
+    (body, (note, format!("[^{added}]: ").chars().count()))
+}
+
+/// The position of the note referred to by the marker under the cursor, or
+/// the position of the marker of the note under the cursor
+pub fn counterpart(lines: &[String], (row, col): (usize, usize)) -> Option<(usize, usize)> {
+    let line = lines.get(row)?;
+    if let Some(caps) = NOTE.captures(line) {
+        let marker = format!("[^{}]", &caps[1]);
+        return lines.iter().enumerate()
+            .filter(|(_, l)| !NOTE.is_match(l))
+            .find_map(|(r, l)| l.find(&marker).map(|i| (r, l[..i].chars().count())));
+    }
+    let n = MARKER.captures_iter(line)
+        .find(|caps| {
+            let m = caps.get(0).unwrap();
+            let start = line[..m.start()].chars().count();
+            let end = line[..m.end()].chars().count();
+            (start..end).contains(&col)
+        })
+        .and_then(|caps| caps.get(1).map(|n| n.as_str().to_string()))?;
+    lines.iter().position(|l| NOTE.captures(l).is_some_and(|caps| caps[1] == n))
+        .map(|r| (r, lines[r].chars().count()))
+}
@@ -0,0 +1,137 @@
+//! This module restyles the cells of the text view once the textarea has
+//! been rendered. The textarea only knows how to highlight the search
+//! pattern, so everything else that must be flagged in the text (e.g.
+//! lines which are too long) is painted over it.
+
+use std::ops::Range;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use tui_textarea::TextArea;
+use unicode_width::UnicodeWidthChar;
+
+/// Mirrors the scrolling state of the textarea
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Viewport {
+    /// The first visible row of the buffer
+    top : u16,
+    /// The first visible column of the display
+    left: u16,
+    /// The area where the text (and line numbers) are drawn
+    area: Rect,
+    /// The width of the line numbers
+    gutter: u16,
+    /// The width of a tab character
+    tab : u8,
+}
+
+impl Viewport {
+    /// Updates the viewport exactly the same way the textarea does when it
+    /// is rendered in the given area
+    pub fn update(&mut self, text: &TextArea, area: Rect) {
+        fn next_scroll_top(prev_top: u16, cursor: u16, length: u16) -> u16 {
+            if cursor < prev_top {
+                cursor
+            } else if prev_top + length <= cursor {
+                cursor + 1 - length
+            } else {
+                prev_top
+            }
+        }
+        let area = text.block().map(|b| b.inner(area)).unwrap_or(area);
+        let (row, col) = text.cursor();
+        self.top  = next_scroll_top(self.top, row as u16, area.height);
+        self.left = next_scroll_top(self.left, col as u16, area.width);
+        self.area = area;
+        self.tab  = text.tab_length();
+        self.gutter = if text.line_number_style().is_some() {
+            (text.lines().len() as f64).log10() as u16 + 3
+        } else {
+            0
+        };
+    }
+
+    /// The rows of the buffer that are currently visible
+    pub fn rows(&self) -> Range<usize> {
+        self.top as usize..(self.top + self.area.height) as usize
+    }
+
+    /// True iff the given row of the buffer is visible
+    pub fn shows(&self, row: usize) -> bool {
+        self.rows().contains(&row)
+    }
+
+    /// Restyles the cells displaying the line number of the given row
+    pub fn restyle_gutter(&self, buf: &mut Buffer, row: usize, style: Style) {
+        self.paint(buf, row, 0..self.gutter, style);
+    }
+
+    /// Restyles the cells displaying the characters `cols` of the given row
+    pub fn restyle(&self, buf: &mut Buffer, row: usize, line: &str, cols: Range<usize>, style: Style) {
+        if let Some(cols) = self.display(line, cols) {
+            self.paint(buf, row, cols, style);
+        }
+    }
+
+    /// Displays the given symbol instead of the character `col` of the row
+    pub fn replace(&self, buf: &mut Buffer, row: usize, line: &str, col: usize, symbol: &str, style: Style) {
+        if !self.shows(row) {
+            return;
+        }
+        if let Some(cols) = self.display(line, col..col + 1) {
+            if let Some(x) = self.x(cols.start) {
+                let y = self.area.y + (row - self.top as usize) as u16;
+                buf.get_mut(x, y).set_symbol(symbol).set_style(style);
+            }
+        }
+    }
+
+    /// The position on screen of the character `col` of the row (which may
+    /// be the end of the line), if it is visible
+    pub fn position(&self, line: &str, (row, col): (usize, usize)) -> Option<(u16, u16)> {
+        if !self.shows(row) {
+            return None;
+        }
+        let cols = self.display(&format!("{line} "), col..col + 1)?;
+        let y = self.area.y + (row - self.top as usize) as u16;
+        self.x(cols.start).map(|x| (x, y))
+    }
+
+    /// The display columns (line numbers included) of the characters `cols`
+    /// of the line
+    fn display(&self, line: &str, cols: Range<usize>) -> Option<Range<u16>> {
+        let mut start = None;
+        let mut end   = 0;
+        let mut width = 0;
+        for (i, c) in line.chars().enumerate() {
+            if i == cols.start {
+                start = Some(width);
+            }
+            width += match c {
+                '\t' if self.tab > 0 => self.tab as u16 - width % self.tab as u16,
+                c => c.width().unwrap_or(0) as u16,
+            };
+            if i < cols.end {
+                end = width;
+            }
+        }
+        start.map(|start| self.gutter + start..self.gutter + end)
+    }
+
+    /// The screen abscissa of the given display column (if it is visible)
+    fn x(&self, col: u16) -> Option<u16> {
+        (col >= self.left && col - self.left < self.area.width).then(|| self.area.x + col - self.left)
+    }
+
+    /// Restyles the given display columns of a row
+    fn paint(&self, buf: &mut Buffer, row: usize, cols: Range<u16>, style: Style) {
+        if !self.shows(row) {
+            return;
+        }
+        let y = self.area.y + (row - self.top as usize) as u16;
+        for x in cols.filter_map(|col| self.x(col)) {
+            buf.get_mut(x, y).set_style(style);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! This module defines the key bindings as they are written in the
+//! configuration, e.g. `"f2"`, `"ctrl+s"` or `"shift+enter"`, along with
+//! the keys of the command mode and the key map which rebinds them.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::errors;
+
+/// A key along with the modifiers that must be held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// True iff the event is a press of this key. Shift is ignored for the
+    /// characters since it is already reflected by the character itself.
+    pub fn matches(&self, event: &Event) -> bool {
+        match event {
+            Event::Key(KeyEvent { kind: KeyEventKind::Press, code, modifiers, .. }) => {
+                if let KeyCode::Char(_) = code {
+                    *code == self.code && modifiers.difference(KeyModifiers::SHIFT) == self.modifiers.difference(KeyModifiers::SHIFT)
+                } else {
+                    *code == self.code && *modifiers == self.modifiers
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// True iff a press of one of the keys would also trigger the other
+    pub fn overlaps(&self, other: &KeyBinding) -> bool {
+        self.matches(&other.press())
+    }
+
+    /// The event of a press of this key
+    pub fn press(&self) -> Event {
+        Event::Key(KeyEvent::new(self.code, self.modifiers))
+    }
+}
+
+/// An action of the command mode: its name, its default key and what it does
+pub type Command = (&'static str, &'static str, &'static str);
+
+/// The actions of the command mode (movements included) with their default
+/// key and what they do, by category. An action may have several keys. The
+/// command palette lists them, and runs an action by pressing its key.
+pub const COMMAND_KEYS: &[(&str, &[Command])] = &[
+    ("Files", &[
+        ("quit", "q", "quit"),
+        ("force-quit", "Q", "quit without saving"),
+        ("quit-any-mode", "ctrl+q", "quit (any mode)"),
+        ("open-dir", "o", "open a directory"),
+        ("open-recent", "ctrl+o", "open a recent directory"),
+        ("open-file", "f", "open a file (id or path)"),
+        ("new-page", "+", "create a new page"),
+        ("next-file", "n", "next file"),
+        ("previous-file", "p", "previous file"),
+        ("enter", "enter", "configurable enter action"),
+        ("save", "ctrl+s", "save"),
+        ("save-as", "W", "save as"),
+        ("scratch", "S", "toggle the scratch buffer"),
+    ]),
+    ("Navigation", &[
+        ("goto", "g", "go to a line of the file (gg: top)"),
+        ("bottom", "G", "go to the bottom of the file"),
+        ("goto-collection", "ctrl+g", "go to a line of the collection"),
+        ("empty-line", "a", "go to the first empty line"),
+        ("footnote-jump", "]", "jump to the footnote counterpart"),
+        ("switch-pane", "ctrl+w", "switch to the other pane of the split view (tab too)"),
+        ("next-word", "w", "next word"),
+        ("next-word", "ctrl+right", "next word"),
+        ("previous-word", "b", "previous word"),
+        ("previous-word", "ctrl+left", "previous word"),
+        ("line-start", "^", "start of line"),
+        ("line-start", "home", "start of line"),
+        ("line-end", "$", "end of line"),
+        ("line-end", "end", "end of line"),
+        ("previous-paragraph", "{", "previous paragraph"),
+        ("previous-paragraph", "ctrl+u", "previous paragraph"),
+        ("previous-paragraph", "pageup", "previous paragraph"),
+        ("next-paragraph", "}", "next paragraph"),
+        ("next-paragraph", "ctrl+d", "next paragraph"),
+        ("next-paragraph", "pagedown", "next paragraph"),
+        ("previous-char", "left", "previous character"),
+        ("next-char", "right", "next character"),
+        ("previous-line", "up", "previous line"),
+        ("next-line", "down", "next line"),
+    ]),
+    ("Editing", &[
+        ("input", "i", "input mode"),
+        ("append", "A", "input mode at the end of the buffer"),
+        ("history", "h", "history mode"),
+        ("undo", "u", "undo"),
+        ("redo", "ctrl+r", "redo"),
+        ("select", "v", "selection mode"),
+        ("select", "space", "selection mode"),
+        ("copy", "*", "copy the file (or the selection) to the clipboard"),
+        ("paste", "ctrl+v", "paste the clipboard"),
+        ("store-register", "\"", "store the file in a register"),
+        ("insert-register", "'", "insert a register"),
+        ("list-registers", "@", "list the registers"),
+        ("special-chars", "c", "special characters"),
+        ("heading", "#", "insert a heading"),
+        ("page-break", "=", "insert a page break"),
+        ("footnote", "[", "insert a footnote"),
+        ("split-lines", "l", "split the long lines"),
+        ("split-paragraph", "L", "split the long lines of the paragraph"),
+        ("join-lines", "J", "join the line with the next one"),
+        ("trim", "t", "trim the trailing whitespace of the lines"),
+        ("squeeze", "s", "collapse the runs of blank lines"),
+        ("preview-cuts", "P", "preview the cuts of the long lines"),
+        ("reflow", "j", "reflow the paragraph to a width"),
+        ("table", "|", "delimit the columns of the table"),
+        ("capitalize", "U", "capitalize the sentence starts"),
+    ]),
+    ("Search", &[
+        ("search", "/", "search mode"),
+        ("focus", "F", "focus on the search matches"),
+        ("search-word", "&", "search the word under the cursor"),
+        ("replace", "r", "replace the search pattern in the file"),
+        ("replace-all", "R", "replace a pattern in all the pages"),
+        ("markup", "T", "turn the search matches into markup"),
+    ]),
+    ("View", &[
+        ("hex", "x", "hex view"),
+        ("compare", "C", "compare two files"),
+        ("diff", "d", "show the changes since the last save"),
+        ("split-view", "Y", "open (or close) a second file in a split view"),
+        ("split-next", "y", "edit the page along with the next one (or close the split view)"),
+        ("scan", "I", "show (or hide) the scanned image of the page"),
+        ("line-numbers", "N", "toggle the line numbers"),
+        ("whitespace", ".", "toggle the whitespace markers"),
+        ("trailing-whitespace", "_", "toggle the trailing whitespace highlight"),
+        ("gauge", "%", "toggle the gauge"),
+        ("distraction-free", "z", "toggle the distraction-free view"),
+        ("peek", "tab", "switch the pane of the split view, or show the title and status line (distraction-free view)"),
+        ("messages", "M", "message log"),
+        ("expand-message", "!", "expand the last message"),
+        ("reset-settings", "Z", "reset the settings"),
+    ]),
+    ("Tools", &[
+        ("palette", ":", "command palette"),
+        ("keys", "?", "list the keys"),
+        ("export", "e", "export"),
+        ("export-markdown", "E", "export as Markdown"),
+        ("split-headings", "B", "split at the headings"),
+        ("flag", "m", "flag the file for review"),
+        ("export-flags", "X", "export the review flags"),
+        ("check", "K", "check the collection is complete"),
+        ("lint", "V", "lint the directory"),
+        ("chapters", "H", "move the pages into their chapters"),
+        ("conflicts", "k", "list the key binding conflicts"),
+        ("reload-config", "O", "reload the configuration"),
+        ("latency", "D", "measure the latency"),
+    ]),
+];
+
+/// All the actions of the command mode, with their default key and what
+/// they do
+pub fn command_keys() -> impl Iterator<Item = &'static Command> {
+    COMMAND_KEYS.iter().flat_map(|(_, keys)| keys.iter())
+}
+
+/// The keys of the actions of the command mode chosen by the user in
+/// `keys.toml` (in the configuration directory), e.g. `save = "f5"`. Such
+/// a key replaces all the default keys of the action, while the actions
+/// which are not listed keep theirs. Only the command mode (movements
+/// included) goes through the key map: the keys of the other modes (the
+/// input, the selection, the prompts and the overlays) cannot be changed.
+#[derive(Debug, Clone, Default)]
+pub struct KeyMap {
+    bindings: BTreeMap<String, KeyBinding>,
+}
+
+impl KeyMap {
+    /// The location of the key map
+    pub fn path() -> Option<std::path::PathBuf> {
+        Config::dir().map(|dir| dir.join("keys.toml"))
+    }
+
+    /// Loads the key map (a missing file binds nothing) along with the
+    /// problems found in the file: the names which are not those of an
+    /// action, and the keys bound to several actions. These are left out of
+    /// the map, so that the actions concerned keep their default keys.
+    pub fn load() -> errors::Result<(Self, Vec<String>)> {
+        let bindings = match Self::path() {
+            Some(path) if path.is_file() => toml::from_str(&std::fs::read_to_string(path)?)?,
+            _ => BTreeMap::new(),
+        };
+        Ok(Self::checked(bindings))
+    }
+
+    /// The key map made of the valid bindings, along with the problems of
+    /// the other ones
+    fn checked(mut bindings: BTreeMap<String, KeyBinding>) -> (Self, Vec<String>) {
+        let mut problems = vec![];
+        let unknown = bindings.keys()
+            .filter(|name| !command_keys().any(|(action, _, _)| action == name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            problems.push(format!("Unknown actions in keys.toml: {}", unknown.join(", ")));
+        }
+        bindings.retain(|name, _| !unknown.contains(name));
+
+        let mut shared = vec![];
+        for (i, (name, key)) in bindings.iter().enumerate() {
+            for (other, binding) in bindings.iter().skip(i + 1) {
+                if key.overlaps(binding) || binding.overlaps(key) {
+                    problems.push(format!("{key} is bound to both {name} and {other} in keys.toml, both keep their default keys"));
+                    shared.extend([name.clone(), other.clone()]);
+                }
+            }
+        }
+        bindings.retain(|name, _| !shared.contains(name));
+        (Self { bindings }, problems)
+    }
+
+    /// The event the command mode must handle in place of the given one: a
+    /// press of the default key of the action bound to it. None when the
+    /// event is a default key of an action which was bound to another key.
+    pub fn translate(&self, event: Event) -> Option<Event> {
+        for (name, binding) in self.bindings.iter() {
+            if binding.matches(&event) {
+                return command_keys()
+                    .find(|(action, _, _)| action == name)
+                    .and_then(|(_, key, _)| key.parse::<KeyBinding>().ok())
+                    .map(|key| key.press());
+            }
+        }
+        let rebound = command_keys()
+            .filter(|(action, _, _)| self.bindings.contains_key(*action))
+            .filter_map(|(_, key, _)| key.parse::<KeyBinding>().ok())
+            .any(|key| key.matches(&event));
+        if rebound { None } else { Some(event) }
+    }
+
+    /// The key of the action, as shown to the user
+    pub fn key(&self, action: &str, default: &str) -> String {
+        self.bindings.get(action).map_or_else(|| default.to_string(), |key| key.to_string())
+    }
+
+    /// The actions of the command mode along with each key which triggers
+    /// them once the key map is applied
+    pub fn commands(&self) -> Vec<(&'static Command, KeyBinding)> {
+        command_keys()
+            .filter_map(|command| {
+                let key = match self.bindings.get(command.0) {
+                    Some(key) => Some(*key),
+                    None      => command.1.parse().ok(),
+                };
+                key.map(|key| (command, key))
+            })
+            .collect()
+    }
+}
+
+/// Describes the configured bindings (named after their setting) which are
+/// bound to the same key as another configured binding or as a key of the
+/// command mode, and the keys of the key map which shadow the key of another
+/// action of the command mode. Since the configured bindings apply to every
+/// mode, any such overlap shadows one of the two.
+pub fn conflicts(bindings: &[(&str, KeyBinding)], keymap: &KeyMap) -> Vec<String> {
+    let mut conflicts = vec![];
+    let commands = keymap.commands();
+    for (i, (name, key)) in bindings.iter().enumerate() {
+        for (other, binding) in bindings[i + 1..].iter() {
+            if key.overlaps(binding) || binding.overlaps(key) {
+                conflicts.push(format!("{key} is bound to both {name} and {other}"));
+            }
+        }
+        for ((_, _, what), builtin) in commands.iter() {
+            if key.overlaps(builtin) || builtin.overlaps(key) {
+                conflicts.push(format!("{key} is bound to both {name} and '{what}' (command mode)"));
+            }
+        }
+    }
+    for (action, key) in keymap.bindings.iter() {
+        for ((other, _, what), builtin) in commands.iter().filter(|((other, _, _), _)| other != action) {
+            if key.overlaps(builtin) || builtin.overlaps(key) {
+                conflicts.push(format!("{key} is bound to both {action} (keys.toml) and '{what}' ({other})"));
+            }
+        }
+    }
+    conflicts
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl std::str::FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (mods, key) = match text.rsplit_once('+') {
+            Some((mods, "")) => (mods.strip_suffix('+').unwrap_or(mods), "+"),
+            Some((mods, key)) => (mods, key),
+            None => ("", text),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for m in mods.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match m.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt"   => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier {m} in {text}")),
+            };
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "enter"     => KeyCode::Enter,
+                "esc"       => KeyCode::Esc,
+                "tab"       => KeyCode::Tab,
+                "backtab"   => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete"    => KeyCode::Delete,
+                "insert"    => KeyCode::Insert,
+                "home"      => KeyCode::Home,
+                "end"       => KeyCode::End,
+                "pageup"    => KeyCode::PageUp,
+                "pagedown"  => KeyCode::PageDown,
+                "up"        => KeyCode::Up,
+                "down"      => KeyCode::Down,
+                "left"      => KeyCode::Left,
+                "right"     => KeyCode::Right,
+                "space"     => KeyCode::Char(' '),
+                f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(format!("unknown key {key} in {text}")),
+                }
+            }
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c)   => write!(f, "{c}"),
+            KeyCode::F(n)      => write!(f, "f{n}"),
+            code => write!(f, "{}", format!("{code:?}").to_lowercase()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_key_bound_to_two_actions_is_rejected() {
+        let bindings = [("save", "f5"), ("quit", "f5"), ("export", "f6")].iter()
+            .map(|(action, key)| (action.to_string(), key.parse().unwrap()))
+            .collect();
+        let (keymap, problems) = KeyMap::checked(bindings);
+        assert_eq!(problems, vec!["f5 is bound to both quit and save in keys.toml, both keep their default keys"]);
+        assert_eq!(keymap.key("save", "ctrl+s"), "ctrl+s");
+        assert_eq!(keymap.key("quit", "q"), "q");
+        assert_eq!(keymap.key("export", "e"), "f6");
+    }
+}
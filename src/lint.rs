@@ -0,0 +1,44 @@
+//! This module implements the lint pass over a whole collection: it reports
+//! the files which do not decode as UTF-8, as well as the lines containing
+//! control characters or trailing whitespace.
+
+use std::fs::read_dir;
+use std::path::Path;
+
+use crate::errors::Result;
+
+/// Checks every file of the directory (dotfiles excepted) and returns a
+/// description of each problem found, in the order of the file names
+pub fn lint(dir: &Path) -> Result<Vec<String>> {
+    let mut files = read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .map(|e| e.path())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut problems = vec![];
+    for path in files {
+        let name  = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let bytes = std::fs::read(&path)?;
+        match std::str::from_utf8(&bytes) {
+            Err(e) => {
+                let line = bytes[..e.valid_up_to()].iter().filter(|b| **b == b'\n').count() + 1;
+                problems.push(format!("{name}:{line}: invalid UTF-8"));
+            },
+            Ok(text) => {
+                for (i, line) in text.split('\n').enumerate() {
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    if let Some(c) = line.chars().find(|c| c.is_control() && *c != '\t') {
+                        problems.push(format!("{name}:{}: control character U+{:04X}", i + 1, c as u32));
+                    }
+                    if line.len() != line.trim_end().len() {
+                        problems.push(format!("{name}:{}: trailing whitespace", i + 1));
+                    }
+                }
+            },
+        }
+    }
+    Ok(problems)
+}
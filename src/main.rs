@@ -1,4 +1,18 @@
 mod errors;
+mod chapters;
+mod clip;
+mod config;
+mod diff;
+mod export;
+mod files;
+mod footnotes;
+mod highlight;
+mod keys;
+mod lint;
+mod recent;
+mod replace;
+mod scan;
+mod session;
 mod term;
 mod app;
 
@@ -6,6 +20,51 @@ use errors::Result;
 use app::App;
 
 fn main() -> Result<()> {
-    App::new()?.run();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(|a| a.as_str()) {
+        Some("lint") => {
+            let dir = args.get(1).map_or(".", |d| d.as_str());
+            let problems = lint::lint(std::path::Path::new(dir))?;
+            for problem in problems.iter() {
+                println!("{problem}");
+            }
+            if !problems.is_empty() {
+                std::process::exit(1);
+            }
+        },
+        Some("replace") if args.len() >= 3 => {
+            let pattern = regex::Regex::new(&args[1])?;
+            let apply = args.iter().any(|a| a == "--apply");
+            let dir = args.iter().skip(3).find(|a| *a != "--apply").map_or(".", |d| d.as_str());
+            let dir = std::path::Path::new(dir);
+            let changes = if apply {
+                replace::apply(dir, &pattern, &args[2], config::Config::load()?.backup)?
+            } else {
+                replace::plan(dir, &pattern)?
+            };
+            for (page, count) in changes.iter() {
+                println!("{}: {count}", page.display());
+            }
+            let total = changes.iter().map(|(_, count)| count).sum::<usize>();
+            let verb = if apply { "replaced" } else { "would be replaced (use --apply)" };
+            println!("{total} matches in {} files {verb}", changes.len());
+        },
+        Some("chapters") => {
+            let apply = args.iter().any(|a| a == "--apply");
+            let dir = args.iter().skip(1).find(|a| *a != "--apply").map_or(".", |d| d.as_str());
+            let config = config::Config::load()?;
+            let moves = chapters::plan(std::path::Path::new(dir), &config.chapters, config.pad.unwrap_or(3))?;
+            for m in moves.iter() {
+                println!("{} -> {}", m.from.display(), m.to.display());
+            }
+            if apply {
+                chapters::apply(&moves)?;
+                println!("{} files moved", moves.len());
+            } else {
+                println!("{} files would be moved (use --apply)", moves.len());
+            }
+        },
+        dir => App::new(dir.map(std::path::PathBuf::from))?.run(),
+    }
     Ok(())
 }
@@ -0,0 +1,62 @@
+//! This module keeps track of the directories that were opened recently (in
+//! the configuration directory) so that they can be opened again without
+//! typing their path, along with the search patterns used recently.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::errors::Result;
+
+/// How many directories are remembered
+pub const MAX_RECENT: usize = 20;
+
+/// The directories opened recently (the most recent first) and the search
+/// patterns (the most recent last)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recent {
+    pub dirs: Vec<String>,
+    pub searches: Vec<String>,
+}
+
+impl Recent {
+    fn path() -> Option<PathBuf> {
+        Config::dir().map(|dir| dir.join("recent.toml"))
+    }
+
+    /// Loads the lists of directories and patterns. A missing file yields
+    /// empty lists
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.is_file() => {
+                let text = std::fs::read_to_string(path)?;
+                Ok(toml::from_str(&text)?)
+            },
+            _ => Ok(Self::default())
+        }
+    }
+
+    /// Stores the lists of directories and patterns (nowhere if there is no
+    /// configuration directory)
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = Self::path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, toml::to_string(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the given directory to the top of the list (that is, it is only
+    /// listed once) and forgets the oldest ones beyond `MAX_RECENT`
+    pub fn push(&mut self, dir: &Path) {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let dir = dir.to_string_lossy().to_string();
+        self.dirs.retain(|d| *d != dir);
+        self.dirs.insert(0, dir);
+        self.dirs.truncate(MAX_RECENT);
+    }
+}
@@ -0,0 +1,54 @@
+//! This module implements the regex replacements applied to all the pages
+//! of a directory at once, e.g. to fix an OCR error that keeps coming back.
+
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::errors::Result;
+use crate::files;
+
+/// The pages (NNN.txt files) of the directory, in order
+fn pages(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut pages = read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "txt"))
+        .filter(|p| p.file_stem().and_then(|s| s.to_str()).is_some_and(|s| s.parse::<u32>().is_ok()))
+        .collect::<Vec<_>>();
+    pages.sort();
+    Ok(pages)
+}
+
+/// The number of matches of the pattern in each page where it occurs (dry
+/// run: nothing gets modified)
+pub fn plan(dir: &Path, pattern: &Regex) -> Result<Vec<(PathBuf, usize)>> {
+    let mut plan = vec![];
+    for page in pages(dir)? {
+        let count = pattern.find_iter(&std::fs::read_to_string(&page)?).count();
+        if count > 0 {
+            plan.push((page, count));
+        }
+    }
+    Ok(plan)
+}
+
+/// Replaces the matches of the pattern in every page and returns the number
+/// of replacements made in each modified page. The pages without any match
+/// are left untouched. When `backup` is set, each page is copied to a
+/// `.NNN.txt.bak` file before it gets modified.
+pub fn apply(dir: &Path, pattern: &Regex, replacement: &str, backup: bool) -> Result<Vec<(PathBuf, usize)>> {
+    let mut done = vec![];
+    for (page, count) in plan(dir, pattern)? {
+        let text = std::fs::read_to_string(&page)?;
+        if backup {
+            let name = page.file_name().unwrap_or_default().to_string_lossy();
+            std::fs::copy(&page, dir.join(format!(".{name}.bak")))?;
+        }
+        files::write(&page, pattern.replace_all(&text, replacement).as_bytes())?;
+        done.push((page, count));
+    }
+    Ok(done)
+}
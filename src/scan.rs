@@ -0,0 +1,55 @@
+//! This module renders the scanned image of a page next to its text. Each
+//! cell of the terminal shows two pixels, one above the other, with a half
+//! block: this works in any terminal which supports true colors.
+
+use std::path::PathBuf;
+
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::{Block, Borders, Widget}};
+
+use crate::errors::Result;
+
+/// The scanned image of a page
+pub struct Scan {
+    path: PathBuf,
+    image: DynamicImage,
+    /// The image resized to the area it was last drawn in
+    resized: Option<(Rect, RgbImage)>,
+}
+
+impl Scan {
+    /// Reads the image in the given file
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let image = image::open(&path)?;
+        Ok(Self { path, image, resized: None })
+    }
+
+    /// Draws the image (along with its file name) in the area, as large as
+    /// it fits without getting distorted
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let name = self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let block = Block::new().borders(Borders::all()).title(name);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.is_empty() {
+            return;
+        }
+        if self.resized.as_ref().map(|(area, _)| *area) != Some(inner) {
+            let resized = self.image
+                .resize(inner.width as u32, inner.height as u32 * 2, FilterType::Triangle)
+                .into_rgb8();
+            self.resized = Some((inner, resized));
+        }
+        let Some((_, image)) = self.resized.as_ref() else { return };
+        let left = inner.x + (inner.width - image.width() as u16) / 2;
+        let rgb = |x: u32, y: u32| image.get_pixel_checked(x, y).map(|p| Color::Rgb(p[0], p[1], p[2]));
+        for y in 0..image.height().div_ceil(2) {
+            for x in 0..image.width() {
+                let cell = buf.get_mut(left + x as u16, inner.y + y as u16);
+                cell.set_char('▀')
+                    .set_fg(rgb(x, 2 * y).unwrap_or(Color::Reset))
+                    .set_bg(rgb(x, 2 * y + 1).unwrap_or(Color::Reset));
+            }
+        }
+    }
+}
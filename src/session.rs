@@ -0,0 +1,70 @@
+//! This module defines the state that is kept in each directory (in a
+//! `.couic` file) so that it gets restored when the directory is opened
+//! again.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Result;
+
+/// Name of the file holding the state of a directory
+pub const SESSION_FILE: &str = ".couic";
+
+/// The state of a directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The view settings chosen for this directory (if any)
+    pub view: Option<ViewSettings>,
+    /// The pattern highlighted in the files of this directory, instead of
+    /// the configured one (if any)
+    pub highlight: Option<String>,
+    /// The page that was last edited (if any)
+    pub page: Option<u32>,
+    /// The position (row, column) of the cursor in that page
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// The settings of the view which can be toggled at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    /// Show the line numbers
+    pub line_numbers: bool,
+    /// Show the spaces and tabs with visible markers
+    pub whitespace: bool,
+    /// Highlight the whitespace at the end of the lines
+    pub trailing: bool,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        Self { line_numbers: true, whitespace: false, trailing: false }
+    }
+}
+
+impl Session {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(SESSION_FILE)
+    }
+
+    /// Loads the state of the directory. A missing file yields the default
+    /// (empty) state
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = Self::path(dir);
+        if path.is_file() {
+            let text = std::fs::read_to_string(path)?;
+            Ok(toml::from_str(&text)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Stores the state of the directory
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let text = toml::to_string(self)?;
+        std::fs::write(Self::path(dir), text)?;
+        Ok(())
+    }
+}
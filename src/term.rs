@@ -1,19 +1,43 @@
 //! This module defines some utility functions to work with the terminal
 
 use std::io::{stdout, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crossterm::{execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}};
+use crossterm::{cursor::{SetCursorStyle, Show}, execute, terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen, LeaveAlternateScreen}};
+use crossterm::event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags};
 use ratatui::{backend::CrosstermBackend, Terminal};
+use crate::config::CursorShape;
 use crate::errors::Result;
 
 /// Convenient alias
 pub type Term = Terminal<CrosstermBackend<Stdout>>;
 
-/// Initializes the terminal
+/// True iff the terminal was asked to report the kind of the key events
+/// (which must then be undone on exit)
+static ENHANCED: AtomicBool = AtomicBool::new(false);
+
+/// Initializes the terminal. A panic restores it before the message gets
+/// printed, so that the shell is left usable.
 pub fn init_term() -> Result<Term> {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // leaving the raw mode or the alternate screen twice is harmless,
+        // so it does not matter that the terminal is reset again on drop
+        disable_raw_mode().ok();
+        if ENHANCED.swap(false, Ordering::SeqCst) {
+            execute!(stdout(), PopKeyboardEnhancementFlags).ok();
+        }
+        execute!(stdout(), LeaveAlternateScreen, SetCursorStyle::DefaultUserShape, Show).ok();
+        hook(info);
+    }));
     enable_raw_mode()?;
     let mut stdout = stdout();
     execute!(stdout, EnterAlternateScreen)?;
+    // without it, the terminals of unix never report the repeats of a key
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(stdout, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        ENHANCED.store(true, Ordering::SeqCst);
+    }
     let term = Terminal::new(CrosstermBackend::new(stdout))?;
     Ok(term)
 }
@@ -21,7 +45,21 @@ pub fn init_term() -> Result<Term> {
 /// Resets the terminal to a useable state by other applications
 pub fn reset_term(term: &mut Term) -> Result<()> {
     disable_raw_mode()?;
-    execute!(term.backend_mut(), LeaveAlternateScreen)?;
+    if ENHANCED.swap(false, Ordering::SeqCst) {
+        execute!(term.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    execute!(term.backend_mut(), LeaveAlternateScreen, SetCursorStyle::DefaultUserShape)?;
     term.show_cursor()?;
     Ok(())
+}
+
+/// Changes the shape of the terminal cursor
+pub fn set_cursor(term: &mut Term, shape: CursorShape) -> Result<()> {
+    let style = match shape {
+        CursorShape::Block     => SetCursorStyle::SteadyBlock,
+        CursorShape::Underline => SetCursorStyle::SteadyUnderScore,
+        CursorShape::Bar       => SetCursorStyle::SteadyBar,
+    };
+    execute!(term.backend_mut(), style)?;
+    Ok(())
 }
\ No newline at end of file